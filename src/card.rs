@@ -0,0 +1,347 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+
+/// Enum for all card suits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Suit {
+    Spades,
+    Clubs,
+    Hearts,
+    Diamonds
+}
+
+impl Suit {
+    /// Every suit in the order they are added to a new deck
+    pub const ALL: [Suit; 4] = [Suit::Spades, Suit::Clubs, Suit::Hearts, Suit::Diamonds];
+
+    /// Returns the icon used when printing the suit
+    pub fn symbol(&self) -> char {
+        match self {
+            Suit::Spades => '♠',
+            Suit::Clubs => '♣',
+            Suit::Hearts => '♥',
+            Suit::Diamonds => '♦'
+        }
+    }
+}
+
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl TryFrom<char> for Suit {
+    /// Reads a suit from its icon or its first letter (s, c, h, d)
+    ///
+    /// # Returns
+    ///
+    /// Suit if the character is known, otherwise Err
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c.to_ascii_lowercase() {
+            '♠' | 's' => Ok(Suit::Spades),
+            '♣' | 'c' => Ok(Suit::Clubs),
+            '♥' | 'h' => Ok(Suit::Hearts),
+            '♦' | 'd' => Ok(Suit::Diamonds),
+            _ => Err(format!("Unknown suit '{c}'"))
+        }
+    }
+
+    type Error = String;
+}
+
+/// Enum for all card ranks, ordered from lowest to highest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Rank {
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+    Ace
+}
+
+impl Rank {
+    /// Every rank in the order they are added to a new deck
+    pub const ALL: [Rank; 13] = [
+        Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven, Rank::Eight,
+        Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King, Rank::Ace,
+    ];
+
+    /// Returns the blackjack value of the rank. Aces are counted as 11 and lowered by evaluate when needed
+    pub fn value(&self) -> u8 {
+        match self {
+            Rank::Two => 2,
+            Rank::Three => 3,
+            Rank::Four => 4,
+            Rank::Five => 5,
+            Rank::Six => 6,
+            Rank::Seven => 7,
+            Rank::Eight => 8,
+            Rank::Nine => 9,
+            Rank::Ten | Rank::Jack | Rank::Queen | Rank::King => 10,
+            Rank::Ace => 11
+        }
+    }
+
+    /// Returns the text used when printing the rank
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Rank::Two => "2",
+            Rank::Three => "3",
+            Rank::Four => "4",
+            Rank::Five => "5",
+            Rank::Six => "6",
+            Rank::Seven => "7",
+            Rank::Eight => "8",
+            Rank::Nine => "9",
+            Rank::Ten => "10",
+            Rank::Jack => "J",
+            Rank::Queen => "Q",
+            Rank::King => "K",
+            Rank::Ace => "A"
+        }
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl FromStr for Rank {
+    type Err = String;
+
+    /// Reads a rank from text like "2", "10", "T" or "K". Letters are case-insensitive
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "2" => Ok(Rank::Two),
+            "3" => Ok(Rank::Three),
+            "4" => Ok(Rank::Four),
+            "5" => Ok(Rank::Five),
+            "6" => Ok(Rank::Six),
+            "7" => Ok(Rank::Seven),
+            "8" => Ok(Rank::Eight),
+            "9" => Ok(Rank::Nine),
+            "10" | "T" => Ok(Rank::Ten),
+            "J" => Ok(Rank::Jack),
+            "Q" => Ok(Rank::Queen),
+            "K" => Ok(Rank::King),
+            "A" => Ok(Rank::Ace),
+            _ => Err(format!("Unknown rank '{s}'"))
+        }
+    }
+}
+
+/// Card struct. Cards are ordered by rank first and suit second
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit
+}
+
+impl Card {
+    pub fn new(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.rank, self.suit)
+    }
+}
+
+impl FromStr for Card {
+    type Err = String;
+
+    /// Reads a card from text like "10♥", "Th" or "as". The last character is the suit
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        // Split the suit off the end so that only the rank is left
+        let mut chars = s.chars();
+        let suit = match chars.next_back() {
+            Some(c) => Suit::try_from(c)?,
+            None => return Err(String::from("Cannot parse a card from empty text"))
+        };
+        let rank = chars.as_str().parse::<Rank>()?;
+
+        Ok(Card { rank, suit })
+    }
+}
+
+/// Creates a sorted deck of 52 cards
+///
+/// # Returns
+///
+/// Vec of cards
+pub fn new_deck() -> Vec<Card> {
+    let mut deck = Vec::with_capacity(52);
+
+    // Add one card of each type to the vector
+    for suit in Suit::ALL {
+        for rank in Rank::ALL {
+            deck.push(Card::new(rank, suit));
+        }
+    }
+
+    deck
+}
+
+/// Value of a set of cards with everything the rules need to know about it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HandValue {
+    /// Total with every ace counted as 1
+    pub hard: u8,
+    /// Total with one ace counted as 11, if the hand has an ace and that doesn't bust it
+    pub soft: Option<u8>,
+    /// Amount of cards in the hand
+    pub cards: usize
+}
+
+impl HandValue {
+    /// Returns the best total of the hand
+    pub fn total(&self) -> u8 {
+        self.soft.unwrap_or(self.hard)
+    }
+
+    /// Returns true if an ace in the hand is counted as 11
+    pub fn is_soft(&self) -> bool {
+        self.soft.is_some()
+    }
+
+    /// Returns true if the hand is over 21
+    pub fn is_bust(&self) -> bool {
+        self.hard > 21
+    }
+
+    /// Returns true if the hand is a two card 21. Whether a split hand counts as a natural is up to the caller
+    pub fn is_natural(&self) -> bool {
+        self.cards == 2 && self.total() == 21
+    }
+
+    /// Returns true if the hand has at least five cards without busting
+    pub fn is_five_card(&self) -> bool {
+        self.cards >= 5 && !self.is_bust()
+    }
+}
+
+impl fmt::Display for HandValue {
+    /// Formats the total. Soft totals below 21 show both values, e.g. "7/17"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.soft {
+            Some(soft) if soft < 21 => write!(f, "{}/{soft}", self.hard),
+            _ => write!(f, "{}", self.total())
+        }
+    }
+}
+
+/// Evaluates a slice of cards
+///
+/// # Returns
+///
+/// HandValue with the hard and the soft total
+pub fn evaluate(cards: &[Card]) -> HandValue {
+    // Add up card values, counting every ace as 1
+    let hard: u8 = cards.iter().fold(0, |sum, card| {
+        let value = if card.rank == Rank::Ace { 1 } else { card.rank.value() };
+        sum.saturating_add(value)
+    });
+    let has_ace = cards.iter().any(|card| card.rank == Rank::Ace);
+
+    // Only one ace can ever count as 11, two would already make 22
+    let soft = if has_ace && hard <= 11 { Some(hard + 10) } else { None };
+
+    HandValue { hard, soft, cards: cards.len() }
+}
+
+/// Calculates the best total value of a slice of cards
+///
+/// # Returns
+///
+/// u8
+pub fn hand_value(cards: &[Card]) -> u8 {
+    evaluate(cards).total()
+}
+
+/// Formats cards as a comma separated list, e.g. "10♥, A♠"
+pub fn format_cards(cards: &[Card]) -> String {
+    cards.iter().map(|card| card.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the cards of the comma separated text
+    fn cards(text: &str) -> Vec<Card> {
+        text.split(',').map(|card| card.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn cards_are_read_from_icons_and_letters() {
+        assert_eq!("10♥".parse(), Ok(Card::new(Rank::Ten, Suit::Hearts)));
+        assert_eq!("Th".parse(), Ok(Card::new(Rank::Ten, Suit::Hearts)));
+        assert_eq!(" as ".parse(), Ok(Card::new(Rank::Ace, Suit::Spades)));
+        assert_eq!("qD".parse(), Ok(Card::new(Rank::Queen, Suit::Diamonds)));
+        assert_eq!("7♣".parse(), Ok(Card::new(Rank::Seven, Suit::Clubs)));
+    }
+
+    #[test]
+    fn broken_cards_tell_what_is_wrong() {
+        assert_eq!("".parse::<Card>(), Err(String::from("Cannot parse a card from empty text")));
+        assert_eq!("10x".parse::<Card>(), Err(String::from("Unknown suit 'x'")));
+        assert_eq!("1h".parse::<Card>(), Err(String::from("Unknown rank '1'")));
+        assert_eq!("h".parse::<Card>(), Err(String::from("Unknown rank ''")));
+    }
+
+    #[test]
+    fn cards_are_printed_with_suit_icons() {
+        assert_eq!(Card::new(Rank::Ten, Suit::Hearts).to_string(), "10♥");
+        assert_eq!(format_cards(&cards("As, Kd, 2c")), "A♠, K♦, 2♣");
+        assert_eq!(format_cards(&[]), "");
+
+        // Every printed card reads back as itself
+        for card in new_deck() {
+            assert_eq!(card.to_string().parse(), Ok(card));
+        }
+    }
+
+    #[test]
+    fn new_deck_has_every_card_once() {
+        let mut deck = new_deck();
+        deck.sort();
+        deck.dedup();
+
+        assert_eq!(deck.len(), 52);
+    }
+
+    #[test]
+    fn aces_count_as_11_while_they_dont_bust_the_hand() {
+        let value = evaluate(&cards("As, 6h"));
+        assert_eq!((value.hard, value.soft, value.total()), (7, Some(17), 17));
+        assert_eq!(value.to_string(), "7/17");
+
+        let value = evaluate(&cards("As, 6h, 9d"));
+        assert_eq!((value.total(), value.is_soft()), (16, false));
+        assert_eq!(value.to_string(), "16");
+
+        assert_eq!(evaluate(&cards("As, Ah, 9d")).total(), 21);
+        assert_eq!(evaluate(&cards("As, Kh")).to_string(), "21");
+        assert!(evaluate(&cards("As, Kh")).is_natural());
+        assert!(!evaluate(&cards("7s, 7h, 7d")).is_natural());
+        assert!(evaluate(&cards("Ks, 5h, 7d")).is_bust());
+        assert!(evaluate(&cards("2s, 2h, 3d, 3c, 4s")).is_five_card());
+    }
+}
@@ -0,0 +1,370 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Rank, Suit};
+
+// Cards in one deck, for working out the decks left in the shoe
+const CARDS_PER_DECK: f64 = 52.0;
+// Tags of a balanced system can add up to a tiny bit off 0 when they are fractions
+const BALANCE_TOLERANCE: f64 = 1e-9;
+
+/// Amount of cards of every rank dealt from the shoe since it was shuffled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DealtCards {
+    /// Indexed in the order of Rank::ALL
+    counts: [u16; 13]
+}
+
+impl DealtCards {
+    pub fn add(&mut self, rank: Rank) {
+        self.counts[rank as usize] += 1;
+    }
+
+    /// Takes back a card of the rank, e.g. a hole card that hasn't been seen yet
+    pub fn remove(&mut self, rank: Rank) {
+        self.counts[rank as usize] = self.counts[rank as usize].saturating_sub(1);
+    }
+
+    /// Returns the amount of cards of the rank dealt
+    pub fn of(&self, rank: Rank) -> u16 {
+        self.counts[rank as usize]
+    }
+
+    /// Returns the amount of every card dealt
+    pub fn total(&self) -> u32 {
+        self.counts.iter().map(|&count| count as u32).sum()
+    }
+}
+
+/// Tags of the built-in systems for the card values 2, 3, 4, 5, 6, 7, 8, 9, 10 and ace
+const HI_LO: [f64; 10] = [1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, -1.0, -1.0];
+const KO: [f64; 10] = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, -1.0, -1.0];
+const HI_OPT_I: [f64; 10] = [0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, -1.0, 0.0];
+const HI_OPT_II: [f64; 10] = [1.0, 1.0, 2.0, 2.0, 1.0, 1.0, 0.0, 0.0, -2.0, 0.0];
+const OMEGA_II: [f64; 10] = [1.0, 1.0, 2.0, 2.0, 2.0, 1.0, 0.0, -1.0, -2.0, 0.0];
+const ZEN: [f64; 10] = [1.0, 1.0, 2.0, 2.0, 2.0, 1.0, 0.0, 0.0, -2.0, -1.0];
+const WONG_HALVES: [f64; 10] = [0.5, 1.0, 1.0, 1.5, 1.0, 0.5, 0.0, -0.5, -1.0, -1.0];
+
+/// A card counting system: the tag every card value adds to the running count and where the count starts
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountingSystem {
+    pub name: String,
+    /// Tags of the card values 2 to 11, aces being 11
+    tags: [f64; 10],
+    /// The tags of a deck add up to 0. Only balanced systems have a true count
+    pub balanced: bool,
+    /// Running count of a fresh shoe is the initial count plus the initial count per deck for every deck
+    pub initial_count: f64,
+    pub initial_count_per_deck: f64,
+    /// Aces are counted on the side, for systems that tag them 0
+    pub ace_side_count: bool
+}
+
+impl CountingSystem {
+    /// Creates a system from the tags of the card values 2 to 10 and ace. It's balanced if the tags of a deck add up to 0
+    fn new(name: &str, tags: [f64; 10], initial_count: f64, initial_count_per_deck: f64, ace_side_count: bool) -> CountingSystem {
+        let mut system = CountingSystem {
+            name: String::from(name),
+            tags,
+            balanced: false,
+            initial_count,
+            initial_count_per_deck,
+            ace_side_count
+        };
+        system.balanced = system.deck_total().abs() < BALANCE_TOLERANCE;
+
+        system
+    }
+
+    pub fn hi_lo() -> CountingSystem {
+        CountingSystem::new("Hi-Lo", HI_LO, 0.0, 0.0, false)
+    }
+
+    /// Returns every built-in system. Hi-Lo comes first
+    pub fn built_in() -> Vec<CountingSystem> {
+        vec![
+            CountingSystem::hi_lo(),
+            // KO starts at 4 - 4 per deck, so that its key count is the same for every shoe
+            CountingSystem::new("KO", KO, 4.0, -4.0, false),
+            CountingSystem::new("Hi-Opt I", HI_OPT_I, 0.0, 0.0, true),
+            CountingSystem::new("Hi-Opt II", HI_OPT_II, 0.0, 0.0, true),
+            CountingSystem::new("Omega II", OMEGA_II, 0.0, 0.0, true),
+            CountingSystem::new("Zen", ZEN, 0.0, 0.0, false),
+            CountingSystem::new("Wong Halves", WONG_HALVES, 0.0, 0.0, false)
+        ]
+    }
+
+    /// Returns the tag of a rank. Every ten-value rank has the same tag
+    pub fn tag(&self, rank: Rank) -> f64 {
+        self.tags[(rank.value() - 2) as usize]
+    }
+
+    /// Returns the sum of the tags of one deck
+    fn deck_total(&self) -> f64 {
+        Rank::ALL.iter().map(|&rank| self.tag(rank) * Suit::ALL.len() as f64).sum()
+    }
+
+    /// Returns the running count of a fresh shoe with the passed amount of decks
+    pub fn initial_running_count(&self, decks: u8) -> f64 {
+        self.initial_count + self.initial_count_per_deck * decks as f64
+    }
+
+    /// Counts the seen cards of a shoe with the passed amount of decks and cards that haven't been seen
+    pub fn count(&self, seen: &DealtCards, unseen: usize, decks: u8) -> Count {
+        let tags: f64 = Rank::ALL.iter().map(|&rank| self.tag(rank) * seen.of(rank) as f64).sum();
+
+        let aces = self.ace_side_count.then(|| {
+            let in_shoe = decks as u32 * Suit::ALL.len() as u32;
+            AceCount {
+                left: in_shoe.saturating_sub(seen.of(Rank::Ace) as u32),
+                even_share: unseen as f64 / Rank::ALL.len() as f64
+            }
+        });
+
+        Count {
+            running: self.initial_running_count(decks) + tags,
+            decks_remaining: unseen as f64 / CARDS_PER_DECK,
+            balanced: self.balanced,
+            aces
+        }
+    }
+}
+
+impl Default for CountingSystem {
+    fn default() -> Self {
+        CountingSystem::hi_lo()
+    }
+}
+
+impl fmt::Display for CountingSystem {
+    /// Formats the system with its tags, e.g. "Hi-Lo | 2-6: +1 | 7-9: 0 | 10, A: -1 | balanced"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        // Card values next to each other with the same tag are written as one range
+        let values: Vec<u8> = (2..=11).collect();
+        for group in values.chunk_by(|a, b| self.tags[(a - 2) as usize] == self.tags[(b - 2) as usize]) {
+            let name = |value: u8| if value == 11 { String::from("A") } else { value.to_string() };
+            let (first, last) = (group[0], group[group.len() - 1]);
+            let tag = self.tags[(first - 2) as usize];
+            let tag = if tag == 0.0 { String::from("0") } else { format!("{tag:+}") };
+
+            match group.len() {
+                1 => write!(f, " | {}: {tag}", name(first))?,
+                2 => write!(f, " | {}, {}: {tag}", name(first), name(last))?,
+                _ => write!(f, " | {}-{}: {tag}", name(first), name(last))?
+            };
+        }
+
+        write!(f, " | {}", if self.balanced { "balanced" } else { "unbalanced" })?;
+        if self.initial_count != 0.0 || self.initial_count_per_deck != 0.0 {
+            write!(f, ", starts at {}, {:+} per deck", self.initial_count, self.initial_count_per_deck)?;
+        }
+        if self.ace_side_count {
+            write!(f, " | ace side count")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a yes or no value of a system definition
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "yes" | "true" | "on" => Ok(true),
+        "no" | "false" | "off" => Ok(false),
+        _ => Err(format!("Expected yes or no, got '{value}'"))
+    }
+}
+
+/// Reads a tag or a count of a system definition, e.g. "+1", "-0.5" or "4"
+fn parse_number(value: &str) -> Result<f64, String> {
+    value.parse::<f64>().ok().filter(|number| number.is_finite()).ok_or_else(|| format!("Expected a number, got '{value}'"))
+}
+
+impl FromStr for CountingSystem {
+    type Err = String;
+
+    /// Reads a system definition made of "key = value" lines. Empty lines and lines starting with # are skipped
+    ///
+    /// `name` is required. Every rank (2-10, A) has its tag as a key, e.g. `5 = +1`, and ranks left out are tagged 0.
+    /// J, Q, K and T are the same as 10. `balanced`, `initial count`, `initial count per deck` and `ace side count`
+    /// are optional. A system is balanced if the tags of a deck add up to 0, and `balanced` has to agree with that
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut tags: [Option<f64>; 10] = [None; 10];
+        let mut balanced = None;
+        let mut initial_count = 0.0;
+        let mut initial_count_per_deck = 0.0;
+        let mut ace_side_count = false;
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |err: String| format!("Line {}: {err}", index + 1);
+            let (key, value) = line.split_once('=').ok_or_else(|| error(String::from("Expected a line like 'name = value'")))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match key.to_lowercase().as_str() {
+                "name" if value.is_empty() => return Err(error(String::from("Name can't be empty"))),
+                "name" => name = Some(String::from(value)),
+                "balanced" => balanced = Some(parse_yes_no(value).map_err(error)?),
+                "initial count" => initial_count = parse_number(value).map_err(error)?,
+                "initial count per deck" => initial_count_per_deck = parse_number(value).map_err(error)?,
+                "ace side count" => ace_side_count = parse_yes_no(value).map_err(error)?,
+                _ => {
+                    let rank = key.parse::<Rank>().map_err(|_| error(format!("Unknown key '{key}'")))?;
+                    let tag = &mut tags[(rank.value() - 2) as usize];
+                    if tag.is_some() {
+                        return Err(error(format!("Tag for {} is set twice", if rank.value() == 10 { "10-value cards" } else { rank.symbol() })));
+                    }
+                    *tag = Some(parse_number(value).map_err(error)?);
+                }
+            };
+        }
+
+        let name = name.ok_or_else(|| String::from("A counting system needs a name, e.g. 'name = Red Seven'"))?;
+        let system = CountingSystem::new(&name, tags.map(|tag| tag.unwrap_or(0.0)), initial_count, initial_count_per_deck, ace_side_count);
+
+        match balanced {
+            Some(true) if !system.balanced => Err(format!("{name} is said to be balanced, but the tags of a deck add up to {:+}", system.deck_total())),
+            Some(false) if system.balanced => Err(format!("{name} is said to be unbalanced, but the tags of a deck add up to 0")),
+            _ => Ok(system)
+        }
+    }
+}
+
+/// Aces that haven't been seen, for systems with an ace side count
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AceCount {
+    pub left: u32,
+    /// Aces the unseen cards would have if aces were spread evenly
+    pub even_share: f64
+}
+
+/// Running count of the cards seen since the shuffle and the decks that haven't been seen yet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Count {
+    pub running: f64,
+    pub decks_remaining: f64,
+    /// Unbalanced systems are played by the running count alone
+    pub balanced: bool,
+    pub aces: Option<AceCount>
+}
+
+impl Count {
+    /// Returns the running count divided by the decks remaining, or the running count if every card has been seen
+    pub fn true_count(&self) -> f64 {
+        if self.decks_remaining > 0.0 {
+            self.running / self.decks_remaining
+        } else {
+            self.running
+        }
+    }
+}
+
+impl fmt::Display for Count {
+    /// Formats the count, e.g. "Running count +3 | True count +1.5 | 2.0 decks left". Unbalanced systems have no
+    /// true count and an ace side count is added at the end, e.g. "Aces left 9 (+1.0)"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Running count {:+}", self.running)?;
+        if self.balanced {
+            write!(f, " | True count {:+.1}", self.true_count())?;
+        }
+        write!(f, " | {:.1} decks left", self.decks_remaining)?;
+        if let Some(aces) = self.aces {
+            write!(f, " | Aces left {} ({:+.1})", aces.left, aces.left as f64 - aces.even_share)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_ko_of_the_built_in_systems_is_unbalanced() {
+        for system in CountingSystem::built_in() {
+            assert_eq!(system.balanced, system.name != "KO", "{}", system.name);
+        }
+    }
+
+    #[test]
+    fn reads_a_system_definition() {
+        let system: CountingSystem = "
+            # Red Seven counts red sevens only
+            name = Red Seven
+            2 = +1
+            3 = 1
+            4 = 1
+            5 = 1
+            6 = 1
+            7 = 0.5
+            T = -1
+            a = -1
+            initial count per deck = -2
+            balanced = no
+        ".parse().unwrap();
+
+        assert_eq!(system.name, "Red Seven");
+        assert_eq!(system.tag(Rank::Seven), 0.5);
+        assert_eq!(system.tag(Rank::King), -1.0);
+        assert_eq!(system.tag(Rank::Ace), -1.0);
+        assert_eq!(system.initial_running_count(6), -12.0);
+        assert!(!system.balanced);
+        assert!(!system.ace_side_count);
+    }
+
+    #[test]
+    fn ranks_left_out_are_tagged_zero() {
+        let system: CountingSystem = "name = Fives\n5 = 1\nace side count = yes".parse().unwrap();
+
+        assert_eq!(system.tag(Rank::Five), 1.0);
+        assert_eq!(system.tag(Rank::Nine), 0.0);
+        assert!(system.ace_side_count);
+        assert!("name = Fives\n5 = 1\nbalanced = yes".parse::<CountingSystem>().is_err());
+    }
+
+    #[test]
+    fn fractional_tags_that_add_up_to_zero_are_balanced() {
+        // 0.1 and 0.2 don't add up to exactly 0.3 in floating point
+        let system: CountingSystem = "name = Tenths\n2 = 0.1\n3 = 0.2\n4 = -0.3\nbalanced = yes".parse().unwrap();
+
+        assert!(system.balanced);
+    }
+
+    #[test]
+    fn rejects_broken_definitions() {
+        let error = |text: &str| text.parse::<CountingSystem>().unwrap_err();
+
+        assert_eq!(error("2 = 1"), "A counting system needs a name, e.g. 'name = Red Seven'");
+        assert_eq!(error("name = Twice\n10 = -1\nK = -1"), "Line 3: Tag for 10-value cards is set twice");
+        assert_eq!(error("name = Twice\n5 = 1\n5 = 1"), "Line 3: Tag for 5 is set twice");
+        assert_eq!(error("name = Words\n5 = one"), "Line 2: Expected a number, got 'one'");
+        assert_eq!(error("name = Infinite\n5 = inf"), "Line 2: Expected a number, got 'inf'");
+        assert_eq!(error("name = Knight\nN = 1"), "Line 2: Unknown key 'N'");
+        assert_eq!(error("name = Bare\n5"), "Line 2: Expected a line like 'name = value'");
+        assert_eq!(error("name =\n5 = 1"), "Line 1: Name can't be empty");
+        assert_eq!(error("name = Maybe\nbalanced = maybe"), "Line 2: Expected yes or no, got 'maybe'");
+    }
+
+    #[test]
+    fn true_count_divides_by_the_decks_left() {
+        let mut seen = DealtCards::default();
+        for rank in [Rank::Two, Rank::Three, Rank::Four, Rank::Five] {
+            seen.add(rank);
+        }
+
+        let count = CountingSystem::hi_lo().count(&seen, 104, 6);
+        assert_eq!(count.running, 4.0);
+        assert_eq!(count.true_count(), 2.0);
+        assert_eq!(count.to_string(), "Running count +4 | True count +2.0 | 2.0 decks left");
+    }
+}
@@ -0,0 +1,353 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use blackjack::card::{evaluate, format_cards, new_deck, Card, Rank, Suit};
+use blackjack::engine::Action;
+use blackjack::rules::{RuleSet, SurrenderRule};
+use blackjack::strategy::{basic_strategy, HandKind, Options, Situation};
+
+use crate::game::action_name;
+use crate::input::{GameCommand, InputSource};
+use crate::profiles::pick_profile;
+use crate::save::{load, save};
+use crate::utils::{clear_terminal, notification, NotificationDuration};
+
+// A situation is mastered after this many correct answers in a row
+const MASTERY_STREAK: u32 = 3;
+
+// Share of the situations that are taken from the weak spots while there are any
+const WEAK_SPOT_SHARE: f64 = 0.5;
+
+// Amount of weak spots listed at the start of a drill
+const WEAK_SPOTS_SHOWN: usize = 5;
+
+/// Answers given to one situation in the drill
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DrillRecord {
+    pub attempts: u32,
+    pub correct: u32,
+    /// Correct answers in a row
+    pub streak: u32
+}
+
+impl DrillRecord {
+    fn record(&mut self, correct: bool) {
+        self.attempts += 1;
+        if correct {
+            self.correct += 1;
+            self.streak += 1;
+        } else {
+            self.streak = 0;
+        }
+    }
+
+    fn misses(&self) -> u32 {
+        self.attempts - self.correct
+    }
+
+    /// Returns true if the situation has been answered right enough times in a row
+    pub fn is_mastered(&self) -> bool {
+        self.streak >= MASTERY_STREAK
+    }
+
+    /// Returns true if the situation has been missed and isn't mastered yet
+    pub fn is_weak_spot(&self) -> bool {
+        self.misses() > 0 && !self.is_mastered()
+    }
+}
+
+/// Accuracy of every situation the player has been drilled on
+pub type DrillStats = BTreeMap<Situation, DrillRecord>;
+
+/// Returns a random card of the passed value. Aces are 11 and every ten-value rank is picked evenly
+fn card_of_value<R: Rng + ?Sized>(value: u8, rng: &mut R) -> Option<Card> {
+    let ranks: Vec<Rank> = Rank::ALL.into_iter().filter(|rank| rank.value() == value).collect();
+    let rank = *ranks.choose(rng)?;
+    let suit = *Suit::ALL.choose(rng)?;
+
+    Some(Card::new(rank, suit))
+}
+
+/// Deals cards that make up the passed situation
+///
+/// # Returns
+///
+/// Player's cards and the dealer's upcard, or None if no two cards make the situation
+fn deal_situation<R: Rng + ?Sized>(situation: &Situation, rng: &mut R) -> Option<(Vec<Card>, Card)> {
+    let (first, second) = match situation.hand {
+        HandKind::Pair(rank) => (rank.value(), rank.value()),
+        HandKind::Soft(total) if (13..=20).contains(&total) => (11, total - 11),
+        HandKind::Hard(total) => {
+            // Two different values from 2 to 10 that add up to the total, so that the hand isn't a pair
+            let smallest = total.saturating_sub(10).max(2);
+            let largest = (total - 1) / 2;
+            if smallest > largest {
+                return None;
+            }
+            let first = rng.gen_range(smallest..=largest);
+            (first, total - first)
+        },
+        HandKind::Soft(_) => return None
+    };
+
+    let cards = vec![card_of_value(first, rng)?, card_of_value(second, rng)?];
+    let upcard = card_of_value(situation.upcard.value(), rng)?;
+
+    Some((cards, upcard))
+}
+
+/// Deals two random cards and an upcard from a fresh deck. Naturals are dealt again, since there's nothing to decide
+fn deal_random<R: Rng + ?Sized>(rng: &mut R) -> (Vec<Card>, Card) {
+    loop {
+        let mut deck = new_deck();
+        deck.shuffle(rng);
+
+        let cards = vec![deck[0], deck[1]];
+        if !evaluate(&cards).is_natural() {
+            return (cards, deck[2]);
+        }
+    }
+}
+
+/// Picks the next hand of the drill. Weak spots come up more often the more often they were missed
+fn next_hand<R: Rng + ?Sized>(stats: &DrillStats, rng: &mut R) -> (Vec<Card>, Card) {
+    let weak_spots: Vec<(&Situation, &DrillRecord)> = stats.iter().filter(|(_, record)| record.is_weak_spot()).collect();
+
+    if !weak_spots.is_empty() && rng.gen_bool(WEAK_SPOT_SHARE) {
+        if let Ok((situation, _)) = weak_spots.choose_weighted(rng, |(_, record)| record.misses() + 1) {
+            if let Some(hand) = deal_situation(situation, rng) {
+                return hand;
+            }
+        }
+    }
+
+    deal_random(rng)
+}
+
+/// Returns the name of the group of hands the summary shows the kind in
+fn group_name(kind: &HandKind) -> &'static str {
+    match kind {
+        HandKind::Hard(_) => "Hard hands",
+        HandKind::Soft(_) => "Soft hands",
+        HandKind::Pair(_) => "Pairs"
+    }
+}
+
+/// Prints the accuracy of hard hands, soft hands and pairs and the situations missed the most
+fn print_summary(stats: &DrillStats) {
+    if stats.is_empty() {
+        println!("You haven't practiced yet. Every hand you play here is saved to your profile");
+        return;
+    }
+
+    for group in ["Hard hands", "Soft hands", "Pairs"] {
+        let (attempts, correct) = stats
+            .iter()
+            .filter(|(situation, _)| group_name(&situation.hand) == group)
+            .fold((0, 0), |(attempts, correct), (_, record)| (attempts + record.attempts, correct + record.correct));
+
+        if let Some(percent) = (correct * 100).checked_div(attempts) {
+            println!("{group}: {percent}% right of {attempts}");
+        }
+    }
+
+    let mut weak_spots: Vec<(&Situation, &DrillRecord)> = stats.iter().filter(|(_, record)| record.is_weak_spot()).collect();
+    weak_spots.sort_by_key(|(_, record)| Reverse(record.misses()));
+    if !weak_spots.is_empty() {
+        let names: Vec<String> = weak_spots.iter().take(WEAK_SPOTS_SHOWN).map(|(situation, _)| situation.to_string()).collect();
+        println!("Still practicing: {}", names.join(", "));
+    }
+}
+
+/// Drill mode. Deals random situations without betting, grades every answer against basic strategy for the passed
+/// rules and keeps the accuracy of every situation in the player's profile
+pub fn drill(rules: &RuleSet, input: &mut dyn InputSource) {
+    let Some(id) = pick_profile("Choose a profile to practice with", input) else {
+        return;
+    };
+    let mut player = match load(&id) {
+        Ok(player) => player,
+        Err(err) => {
+            notification(&err.to_string(), NotificationDuration::Long);
+            return;
+        }
+    };
+
+    clear_terminal();
+    println!("--- BASIC STRATEGY DRILL ---");
+    println!("Table rules: {rules}");
+    print_summary(&player.drill);
+    println!("Choose the play for every hand. Type q to go back to the main menu");
+
+    let mut rng = rand::thread_rng();
+    let mut answered = 0;
+    let mut right = 0;
+
+    loop {
+        let (cards, upcard) = next_hand(&player.drill, &mut rng);
+        let situation = Situation::new(&cards, upcard.rank);
+        let value = evaluate(&cards);
+        let options = Options {
+            double: rules.double_rule.allows(value.total()),
+            split: matches!(situation.hand, HandKind::Pair(_)),
+            surrender: rules.surrender != SurrenderRule::NoSurrender
+        };
+        let correct_action = basic_strategy(&cards, upcard.rank, rules, options);
+
+        println!("\n---");
+        println!("Dealer shows: [{upcard}]");
+        println!("Your cards: [{}] ({value})", format_cards(&cards));
+        println!("1. Hit (h) | 2. Stand (s) | 3. Double down (d) | 4. Split (p) | 5. Surrender (r)");
+
+        // Read answers until the player chooses a play that's possible for the hand
+        let action = loop {
+            let Some(line) = input.read_line() else {
+                return;
+            };
+
+            let option = match line.parse::<GameCommand>() {
+                Ok(GameCommand::Play(option)) => option,
+                Ok(GameCommand::Pause) => return,
+                Ok(GameCommand::Help) => {
+                    println!("Answer with the number or the letter of a play, e.g. h for hit. q goes back to the main menu");
+                    continue;
+                },
+                Ok(GameCommand::Hint) => {
+                    notification("No hints in the drill. Answer and you'll see the right play", NotificationDuration::Short);
+                    continue;
+                },
+                Ok(GameCommand::Count) => {
+                    notification("Every hand of the drill is dealt from a fresh deck, so there's nothing to count", NotificationDuration::Short);
+                    continue;
+                },
+                Err(err) => {
+                    notification(&err, NotificationDuration::Short);
+                    continue;
+                }
+            };
+
+            match option.play() {
+                Some(Action::DoubleDown) if !options.double => notification(&format!("You can't double down on this hand. Table rules: {}", rules.double_rule), NotificationDuration::Short),
+                Some(Action::Split) if !options.split => notification("You can only split a pair", NotificationDuration::Short),
+                Some(Action::Surrender) if !options.surrender => notification("Table rules don't allow surrendering", NotificationDuration::Short),
+                Some(action) => break action,
+                None => notification("Choose a play for the hand", NotificationDuration::Short)
+            };
+        };
+
+        let correct = action == correct_action;
+        answered += 1;
+        if correct {
+            right += 1;
+            println!("Correct! {}", action_name(action));
+        } else {
+            println!("Wrong. Basic strategy for {situation}: {}", action_name(correct_action));
+        }
+
+        let record = player.drill.entry(situation).or_default();
+        record.record(correct);
+        if correct && record.streak == MASTERY_STREAK && record.misses() > 0 {
+            println!("You have mastered {situation}");
+        }
+        println!("This drill: {right} of {answered} right");
+
+        if save(&mut player).is_err() {
+            notification("An error occurred when saving", NotificationDuration::Long);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Returns a record of the passed answers, in order
+    fn answered(answers: &[bool]) -> DrillRecord {
+        let mut record = DrillRecord::default();
+        answers.iter().for_each(|&correct| record.record(correct));
+        record
+    }
+
+    #[test]
+    fn situations_are_mastered_after_a_streak_of_right_answers() {
+        let record = answered(&[true, false, true, true]);
+        assert_eq!((record.attempts, record.correct, record.streak), (4, 3, 2));
+        assert!(!record.is_mastered());
+        assert!(record.is_weak_spot());
+
+        let record = answered(&[true, false, true, true, true]);
+        assert!(record.is_mastered());
+        assert!(!record.is_weak_spot());
+
+        // A miss breaks the streak again
+        let record = answered(&[true, true, true, false]);
+        assert_eq!(record.streak, 0);
+        assert!(record.is_weak_spot());
+    }
+
+    #[test]
+    fn situations_never_missed_arent_weak_spots() {
+        assert!(!answered(&[]).is_weak_spot());
+        assert!(!answered(&[true]).is_weak_spot());
+    }
+
+    #[test]
+    fn dealt_cards_make_up_the_situation() {
+        let mut rng = StdRng::seed_from_u64(1);
+        // Situations tell ten-value ranks apart only as tens
+        let pairs = Rank::ALL.into_iter().filter(|rank| !matches!(rank, Rank::Jack | Rank::Queen | Rank::King)).map(HandKind::Pair);
+        let hands = (5..=19).map(HandKind::Hard).chain((13..=20).map(HandKind::Soft)).chain(pairs);
+
+        for hand in hands {
+            for upcard in [Rank::Two, Rank::Ten, Rank::Ace] {
+                let situation = Situation { hand, upcard };
+                let (cards, dealt_upcard) = deal_situation(&situation, &mut rng).unwrap();
+
+                assert_eq!(Situation::new(&cards, dealt_upcard.rank), situation, "{situation}");
+            }
+        }
+    }
+
+    #[test]
+    fn situations_two_cards_cant_make_are_not_dealt() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for hand in [HandKind::Hard(4), HandKind::Hard(20), HandKind::Soft(12), HandKind::Soft(21)] {
+            assert!(deal_situation(&Situation { hand, upcard: Rank::Five }, &mut rng).is_none(), "{hand}");
+        }
+    }
+
+    #[test]
+    fn random_hands_are_never_naturals() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..500 {
+            let (cards, _) = deal_random(&mut rng);
+            assert!(!evaluate(&cards).is_natural());
+        }
+    }
+
+    #[test]
+    fn weak_spots_come_up_about_half_the_time() {
+        let weak = Situation { hand: HandKind::Hard(16), upcard: Rank::Ten };
+        let mastered = Situation { hand: HandKind::Soft(18), upcard: Rank::Nine };
+        let stats = DrillStats::from([(weak, answered(&[false, false])), (mastered, answered(&[false, true, true, true]))]);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut weak_dealt = 0;
+        let mut mastered_dealt = 0;
+        for _ in 0..1000 {
+            let (cards, upcard) = next_hand(&stats, &mut rng);
+            let situation = Situation::new(&cards, upcard.rank);
+            weak_dealt += usize::from(situation == weak);
+            mastered_dealt += usize::from(situation == mastered);
+        }
+
+        assert!((450..600).contains(&weak_dealt), "{weak_dealt}");
+        assert!(mastered_dealt < 20, "{mastered_dealt}");
+    }
+}
@@ -0,0 +1,1161 @@
+use std::fmt;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::card::{evaluate, new_deck, Card, Rank};
+use crate::count::{Count, CountingSystem, DealtCards};
+use crate::hand::Hand;
+use crate::money::{Money, MONEY_OVERFLOW};
+use crate::round::{Round, RoundPhase, Table};
+use crate::rules::{DoubleRule, HoleCardRule, RuleSet, SurrenderRule};
+use crate::strategy::{basic_strategy, declined_even_money_value, expected_value, insurance_value, played_on_value, should_surrender_early, Mistake, Options};
+
+/// Enum for the decisions the player can make
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Hit,
+    Stand,
+    DoubleDown,
+    Split,
+    /// Surrenders the hand, or takes early surrender when it's offered
+    Surrender,
+    /// Buys insurance of the passed amount
+    Insurance(Money),
+    /// Takes even money for a blackjack when the dealer shows an ace
+    EvenMoney,
+    /// Declines an offer of early surrender, insurance or even money
+    Decline
+}
+
+/// Enum for what the engine is waiting for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// A bet starts the next round
+    Betting,
+    /// Early surrender is offered. Answered with Surrender or Decline
+    EarlySurrenderOffer,
+    /// Insurance is offered up to the passed amount. Answered with Insurance or Decline
+    InsuranceOffer(Money),
+    /// Even money is offered for the player's blackjack. Answered with EvenMoney or Decline
+    EvenMoneyOffer,
+    /// The player plays the hand with the passed index
+    PlayerTurn(usize),
+    /// The dealer is playing. Every step draws one card or settles the round
+    DealerTurn
+}
+
+/// Enum for who a card is dealt to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seat {
+    /// Player's hand with the passed index
+    Player(usize),
+    Dealer
+}
+
+/// Enum for the results that end a round without comparing the hands one by one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundOutcome {
+    /// Both the player and the dealer got a blackjack
+    BlackjackPush,
+    Blackjack,
+    EvenMoney,
+    Surrendered,
+    /// Late surrender against a dealer blackjack that wasn't peeked for
+    SurrenderLost,
+    DealerBlackjack,
+    /// Every hand busted
+    Bust
+}
+
+/// Enum for the results of a hand compared to the dealer's hand
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandOutcome {
+    Bust,
+    DealerBust,
+    Push,
+    /// Player was closer to 21
+    Win,
+    /// Dealer was closer to 21
+    Loss
+}
+
+/// Enum for everything that happens at the table. Payouts include the returned bet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    ShuffleStarted { decks: u8 },
+    /// The shoe ran out in the middle of the round and the cards that aren't on the table were shuffled back in
+    DiscardsShuffled,
+    /// The dealer's second card is the hole card while the player acts, so it should be shown only on the dealer's turn
+    CardDealt { seat: Seat, card: Card },
+    /// The hand was split and its second card moved to a new hand right after it
+    HandSplit { hand: usize },
+    BetDoubled { hand: usize },
+    InsuranceSettled { stake: Money, payout: Money },
+    RoundSettled { outcome: RoundOutcome, payout: Money },
+    HandSettled { hand: usize, outcome: HandOutcome, payout: Money },
+    RoundFinished
+}
+
+/// Enum for actions the rules don't allow and errors that end the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineError {
+    /// The action doesn't fit the current state, e.g. hitting while betting
+    InvalidAction,
+    InsufficientFunds,
+    /// Bet is below the table minimum of the passed amount
+    BetTooSmall(Money),
+    /// Bet is above the table maximum of the passed amount
+    BetTooLarge(Money),
+    /// Bet isn't a multiple of the passed amount
+    BetIncrement(Money),
+    /// Insurance has to be greater than 0 and at most the passed amount
+    InvalidInsurance(Money),
+    DoubleAfterHit,
+    DoubleAfterSplit,
+    DoubleNotAllowed(DoubleRule),
+    NotAPair,
+    /// Hands can be split to the passed amount of hands at most
+    TooManySplits(u8),
+    SurrenderNotAllowed,
+    SurrenderAfterHit,
+    ShoeEmpty,
+    Overflow
+}
+
+impl EngineError {
+    /// Returns true if the game can't go on. Other errors only mean that the action wasn't allowed
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, EngineError::ShoeEmpty | EngineError::Overflow)
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::InvalidAction => write!(f, "That can't be done right now"),
+            EngineError::InsufficientFunds => write!(f, "You don't have enough money"),
+            EngineError::BetTooSmall(min) => write!(f, "The table minimum is ${min}"),
+            EngineError::BetTooLarge(max) => write!(f, "The table maximum is ${max}"),
+            EngineError::BetIncrement(increment) => write!(f, "Bets go up in steps of ${increment}"),
+            EngineError::InvalidInsurance(max) => write!(f, "Insurance has to be greater than 0 and at most ${max}"),
+            EngineError::DoubleAfterHit => write!(f, "You can't double down after hitting"),
+            EngineError::DoubleAfterSplit => write!(f, "Table rules don't allow doubling down after a split"),
+            EngineError::DoubleNotAllowed(rule) => write!(f, "You can't double down on this hand. Table rules: {rule}"),
+            EngineError::NotAPair => write!(f, "You can only split a pair"),
+            EngineError::TooManySplits(max) => write!(f, "Table rules allow splitting to {max} hands at most"),
+            EngineError::SurrenderNotAllowed => write!(f, "Table rules don't allow surrendering"),
+            EngineError::SurrenderAfterHit => write!(f, "You can only surrender your first two cards"),
+            EngineError::ShoeEmpty => write!(f, "Shoe is empty, cannot deal cards"),
+            EngineError::Overflow => write!(f, "{MONEY_OVERFLOW}")
+        }
+    }
+}
+
+/// Scrambles a number with the finalizer of SplitMix64, so that numbers next to each other give unrelated results
+fn split_mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns the generator of a shuffle of the table. Every shuffle of every seed gets its own generator, and unlike
+/// StdRng, ChaCha8 gives the same numbers on every platform and release, so a seed always deals the same shoes
+fn shuffle_rng(seed: u64, shuffle: u32) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(split_mix(split_mix(seed) ^ shuffle as u64))
+}
+
+/// Blackjack game for one player without any input or output.
+///
+/// The engine is a state machine: `state` tells what it is waiting for and `bet`, `act` and `step` move it
+/// forward. Every change returns the events that happened in order. The table can be saved at any point and
+/// the game continued from it later with `resume`.
+///
+/// Shuffles are random only through the seed of the table, so the same seed and the same actions always
+/// play out the same way
+pub struct Engine {
+    bankroll: Money,
+    table: Table
+}
+
+impl Engine {
+    /// Creates an engine for a player with the passed bankroll sitting at a new table with a random seed
+    pub fn new(bankroll: Money, rules: RuleSet) -> Engine {
+        Engine::with_seed(bankroll, rules, rand::random())
+    }
+
+    /// Creates an engine for a player with the passed bankroll sitting at a new table whose shoe is shuffled
+    /// from the passed seed
+    pub fn with_seed(bankroll: Money, rules: RuleSet, seed: u64) -> Engine {
+        Engine {
+            bankroll,
+            table: Table::new(rules, seed)
+        }
+    }
+
+    /// Continues the game at a saved table
+    ///
+    /// # Returns
+    ///
+    /// Ok containing the engine or an Err if the round in progress couldn't be continued
+    pub fn resume(bankroll: Money, table: Table) -> Result<Engine, EngineError> {
+        let mut engine = Engine { bankroll, table };
+
+        // The round may have been saved in the middle of steps the engine does on its own
+        engine.advance(&mut Vec::new())?;
+
+        Ok(engine)
+    }
+
+    pub fn bankroll(&self) -> Money {
+        self.bankroll
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.table.rules
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.table.seed
+    }
+
+    /// Returns the table to be saved
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// Returns the count of the cards the player has seen since the shoe was shuffled in the passed system. The
+    /// hole card is counted once it's turned over. If the shoe will be shuffled before the next round, the count of
+    /// a fresh shoe is returned
+    pub fn count(&self, system: &CountingSystem) -> Count {
+        let shoe = &self.table.shoe;
+        if self.state() == State::Betting && shoe.needs_shuffle() {
+            return system.count(&DealtCards::default(), shoe.decks() as usize * new_deck().len(), shoe.decks());
+        }
+
+        let mut seen = self.table.dealt;
+        let mut unseen = shoe.cards_remaining();
+        if let Some(hole_card) = self.hidden_hole_card() {
+            seen.remove(hole_card.rank);
+            unseen += 1;
+        }
+
+        system.count(&seen, unseen, shoe.decks())
+    }
+
+    /// Returns the dealer's second card while it's still face down
+    fn hidden_hole_card(&self) -> Option<Card> {
+        match self.state() {
+            State::EarlySurrenderOffer | State::InsuranceOffer(_) | State::EvenMoneyOffer | State::PlayerTurn(_) => {
+                self.table.round.as_ref()?.dealer_hand.get(1).copied()
+            },
+            State::Betting | State::DealerTurn => None
+        }
+    }
+
+    /// Returns the round in progress, or the previous round once it's finished
+    pub fn round(&self) -> Option<&Round> {
+        self.table.round.as_ref()
+    }
+
+    pub fn round_in_progress(&self) -> bool {
+        self.table.round_in_progress()
+    }
+
+    /// Returns what the engine is waiting for
+    pub fn state(&self) -> State {
+        let Some(round) = self.table.round.as_ref() else {
+            return State::Betting;
+        };
+
+        match round.phase {
+            RoundPhase::EarlySurrender => State::EarlySurrenderOffer,
+            RoundPhase::Insurance if round.hands[0].is_natural() => State::EvenMoneyOffer,
+            RoundPhase::Insurance => State::InsuranceOffer(self.max_insurance(round)),
+            RoundPhase::PlayerTurn => State::PlayerTurn(round.active),
+            // Naturals are settled as soon as the round gets to them, so a round is never left in that phase
+            RoundPhase::Naturals | RoundPhase::DealerTurn => State::DealerTurn,
+            RoundPhase::Finished => State::Betting
+        }
+    }
+
+    /// Checks that the bet fits the table limits and the bankroll
+    ///
+    /// # Returns
+    ///
+    /// Ok or an EngineError telling which limit the bet breaks
+    pub fn check_bet(&self, amount: Money) -> Result<(), EngineError> {
+        let limits = &self.table.rules.limits;
+
+        if amount < limits.min || amount.is_zero() {
+            return Err(EngineError::BetTooSmall(limits.min));
+        }
+        if amount > limits.max {
+            return Err(EngineError::BetTooLarge(limits.max));
+        }
+        if limits.round_down(amount) != amount {
+            return Err(EngineError::BetIncrement(limits.increment));
+        }
+        if amount > self.bankroll {
+            return Err(EngineError::InsufficientFunds);
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the bankroll covers the table minimum
+    pub fn can_bet(&self) -> bool {
+        !self.bankroll.is_zero() && self.bankroll >= self.table.rules.limits.min
+    }
+
+    /// Returns the basic strategy play for the active hand, taking into account which plays the table allows,
+    /// or the better answer to an insurance or even money offer
+    ///
+    /// # Returns
+    ///
+    /// Action or None if there's nothing to decide or basic strategy has no answer
+    pub fn hint(&self) -> Option<Action> {
+        let round = self.table.round.as_ref()?;
+        let upcard = round.dealer_hand.first()?;
+
+        match self.state() {
+            State::PlayerTurn(active) => {
+                let options = Options {
+                    double: self.check_play(round, Action::DoubleDown).is_ok(),
+                    split: self.check_play(round, Action::Split).is_ok(),
+                    surrender: self.check_play(round, Action::Surrender).is_ok()
+                };
+
+                Some(basic_strategy(&round.hands[active].cards, upcard.rank, &self.table.rules, options))
+            },
+            State::InsuranceOffer(_) if insurance_value(upcard.rank) < 0.0 => Some(Action::Decline),
+            State::InsuranceOffer(max_insurance) => Some(Action::Insurance(max_insurance)),
+            State::EvenMoneyOffer if declined_even_money_value(upcard.rank, &self.table.rules) >= 1.0 => Some(Action::Decline),
+            State::EvenMoneyOffer => Some(Action::EvenMoney),
+            State::EarlySurrenderOffer if should_surrender_early(&round.hands[0].cards, upcard.rank, &self.table.rules) => Some(Action::Surrender),
+            State::EarlySurrenderOffer => Some(Action::Decline),
+            State::Betting | State::DealerTurn => None
+        }
+    }
+
+    /// Compares a decision to basic strategy before it's made
+    ///
+    /// # Returns
+    ///
+    /// Mistake with its expected cost or None if the decision is the basic strategy play or there's nothing to compare
+    pub fn review(&self, action: Action) -> Option<Mistake> {
+        let correct = self.hint()?;
+        let round = self.table.round.as_ref()?;
+        let upcard = *round.dealer_hand.first()?;
+        let rules = &self.table.rules;
+        let hand = &round.hands[round.active.min(round.hands.len() - 1)];
+
+        let cost = match (action, correct) {
+            // Any amount of insurance is the same decision
+            (Action::Insurance(_), Action::Insurance(_)) => return None,
+            (Action::Insurance(amount), _) => {
+                -insurance_value(upcard.rank) * amount.cents() as f64 / round.bet.cents().max(1) as f64
+            },
+            (Action::EvenMoney | Action::Decline, _) if matches!(self.state(), State::EvenMoneyOffer) => {
+                (declined_even_money_value(upcard.rank, rules) - 1.0).abs()
+            },
+            (Action::Surrender | Action::Decline, _) if matches!(self.state(), State::EarlySurrenderOffer) => {
+                (played_on_value(&hand.cards, upcard.rank, rules) + 0.5).abs()
+            },
+            (Action::Decline, Action::Insurance(amount)) => {
+                insurance_value(upcard.rank) * amount.cents() as f64 / round.bet.cents().max(1) as f64
+            },
+            _ => {
+                let played = expected_value(&hand.cards, upcard.rank, rules, action)?;
+                let best = expected_value(&hand.cards, upcard.rank, rules, correct)?;
+                best - played
+            }
+        };
+
+        if action == correct {
+            return None;
+        }
+
+        Some(Mistake {
+            cards: hand.cards.clone(),
+            upcard,
+            played: action,
+            correct,
+            bet: hand.bet,
+            // The chart and the estimate can disagree about plays that are almost equal
+            cost: cost.max(0.0)
+        })
+    }
+
+    /// Starts a new round with the passed bet. The shoe is shuffled first if the cut card came out
+    ///
+    /// # Returns
+    ///
+    /// Ok containing the events or an EngineError if a round is in progress or the bet breaks the table limits
+    pub fn bet(&mut self, amount: Money) -> Result<Vec<Event>, EngineError> {
+        if self.state() != State::Betting {
+            return Err(EngineError::InvalidAction);
+        }
+        self.check_bet(amount)?;
+
+        let mut events = Vec::new();
+
+        if self.table.shoe.needs_shuffle() {
+            // Every shuffle gets its own generator from the seed, so a saved table shuffles the same way after loading
+            let mut rng = shuffle_rng(self.table.seed, self.table.shuffles);
+            self.table.shoe.shuffle(&mut rng);
+            self.table.shuffles += 1;
+            self.table.dealt = DealtCards::default();
+            events.push(Event::ShuffleStarted { decks: self.table.shoe.decks() });
+        }
+
+        self.charge(amount)?;
+        self.table.round = Some(Round::new(amount));
+
+        self.with_round(|engine, round| {
+            // Without a hole card the dealer gets only the upcard, unless the player has a blackjack and the dealer
+            // completes the hand right away to see if it's a draw
+            engine.deal_to(round, Seat::Player(0), &mut events)?;
+            engine.deal_to(round, Seat::Player(0), &mut events)?;
+            engine.deal_to(round, Seat::Dealer, &mut events)?;
+            if engine.table.rules.hole_card != HoleCardRule::NoHoleCard || round.hands[0].is_natural() {
+                engine.deal_to(round, Seat::Dealer, &mut events)?;
+            }
+
+            Ok(())
+        })?;
+        self.advance(&mut events)?;
+
+        Ok(events)
+    }
+
+    /// Makes a decision for the player
+    ///
+    /// # Returns
+    ///
+    /// Ok containing the events or an EngineError if the action isn't possible now
+    pub fn act(&mut self, action: Action) -> Result<Vec<Event>, EngineError> {
+        let mut events = Vec::new();
+
+        match (self.state(), action) {
+            (State::EarlySurrenderOffer, Action::Surrender) => self.with_round(|engine, round| {
+                let refund = round.bet.half();
+                engine.finish(round, RoundOutcome::Surrendered, refund, &mut events)
+            })?,
+            (State::EarlySurrenderOffer, Action::Decline) => self.with_round(|_, round| {
+                round.phase = RoundPhase::Insurance;
+                Ok(())
+            })?,
+            (State::InsuranceOffer(max_insurance), Action::Insurance(amount)) => {
+                if amount.is_zero() || amount > max_insurance {
+                    return Err(EngineError::InvalidInsurance(max_insurance));
+                }
+
+                self.with_round(|engine, round| {
+                    engine.charge(amount)?;
+                    round.insurance = amount;
+                    round.phase = RoundPhase::Naturals;
+                    Ok(())
+                })?;
+            },
+            // Even money pays the blackjack 1:1 right away, whatever the dealer has
+            (State::EvenMoneyOffer, Action::EvenMoney) => self.with_round(|engine, round| {
+                let payout = round.bet.checked_mul(2).ok_or(EngineError::Overflow)?;
+                engine.finish(round, RoundOutcome::EvenMoney, payout, &mut events)
+            })?,
+            (State::InsuranceOffer(_) | State::EvenMoneyOffer, Action::Decline) => self.with_round(|_, round| {
+                round.phase = RoundPhase::Naturals;
+                Ok(())
+            })?,
+            (State::PlayerTurn(_), Action::Hit | Action::Stand | Action::DoubleDown | Action::Split | Action::Surrender) => {
+                self.with_round(|engine, round| engine.play_hand(round, action, &mut events))?;
+            },
+            _ => return Err(EngineError::InvalidAction)
+        };
+        self.advance(&mut events)?;
+
+        Ok(events)
+    }
+
+    /// Lets the dealer draw one card, or settles the round once the dealer's hand is complete
+    ///
+    /// # Returns
+    ///
+    /// Ok containing the events or an EngineError if it's not the dealer's turn
+    pub fn step(&mut self) -> Result<Vec<Event>, EngineError> {
+        if self.state() != State::DealerTurn {
+            return Err(EngineError::InvalidAction);
+        }
+
+        let mut events = Vec::new();
+
+        self.with_round(|engine, round| {
+            // Without a hole card the dealer gets the second card only after the player has acted
+            if round.dealer_hand.len() == 1 {
+                return engine.deal_to(round, Seat::Dealer, &mut events);
+            }
+
+            // The dealer doesn't need to draw if the player has no hand left to beat
+            let dealer_plays = !round.surrendered && !round.hands.iter().all(|hand| hand.is_bust());
+            if dealer_plays && dealer_should_hit(&round.dealer_hand, &engine.table.rules) {
+                return engine.deal_to(round, Seat::Dealer, &mut events);
+            }
+
+            engine.settle(round, &mut events)
+        })?;
+
+        Ok(events)
+    }
+
+    /// Returns the largest insurance the player can buy: half of the bet or everything they have left
+    fn max_insurance(&self, round: &Round) -> Money {
+        round.bet.half().min(self.bankroll)
+    }
+
+    /// Runs the passed function with the round taken out of the table, so that it can deal cards and move money
+    /// while changing the round. The round is put back even if the function fails
+    fn with_round<T>(&mut self, f: impl FnOnce(&mut Engine, &mut Round) -> Result<T, EngineError>) -> Result<T, EngineError> {
+        let mut round = self.table.round.take().ok_or(EngineError::InvalidAction)?;
+        let result = f(self, &mut round);
+        self.table.round = Some(round);
+
+        result
+    }
+
+    /// Adds money to the bankroll
+    fn pay(&mut self, amount: Money) -> Result<(), EngineError> {
+        self.bankroll = self.bankroll.checked_add(amount).ok_or(EngineError::Overflow)?;
+        Ok(())
+    }
+
+    /// Takes money from the bankroll
+    fn charge(&mut self, amount: Money) -> Result<(), EngineError> {
+        self.bankroll = self.bankroll.checked_sub(amount).ok_or(EngineError::InsufficientFunds)?;
+        Ok(())
+    }
+
+    /// Shuffles the cards that aren't on the table back into the empty shoe. Only the cards on the table have been
+    /// seen since then
+    fn shuffle_discards(&mut self, round: &Round, events: &mut Vec<Event>) {
+        let in_play: Vec<Card> = round.hands.iter().flat_map(|hand| hand.cards.iter()).chain(round.dealer_hand.iter()).copied().collect();
+
+        let mut rng = shuffle_rng(self.table.seed, self.table.shuffles);
+        self.table.shoe.shuffle_discards(&in_play, &mut rng);
+        self.table.shuffles += 1;
+
+        self.table.dealt = DealtCards::default();
+        for card in &in_play {
+            self.table.dealt.add(card.rank);
+        }
+        events.push(Event::DiscardsShuffled);
+    }
+
+    /// Deals the top card of the shoe to the player's hand or the dealer
+    fn deal_to(&mut self, round: &mut Round, seat: Seat, events: &mut Vec<Event>) -> Result<(), EngineError> {
+        if self.table.shoe.cards_remaining() == 0 {
+            self.shuffle_discards(round, events);
+        }
+
+        let card = self.table.shoe.deal().map_err(|_| EngineError::ShoeEmpty)?;
+        self.table.dealt.add(card.rank);
+
+        match seat {
+            Seat::Player(hand) => round.hands[hand].cards.push(card),
+            Seat::Dealer => round.dealer_hand.push(card)
+        };
+        events.push(Event::CardDealt { seat, card });
+
+        Ok(())
+    }
+
+    /// Pays the passed amount and ends the round with a result that covers every hand
+    fn finish(&mut self, round: &mut Round, outcome: RoundOutcome, payout: Money, events: &mut Vec<Event>) -> Result<(), EngineError> {
+        self.pay(payout)?;
+
+        round.phase = RoundPhase::Finished;
+        events.push(Event::RoundSettled { outcome, payout });
+        events.push(Event::RoundFinished);
+
+        Ok(())
+    }
+
+    /// Moves the round forward until the player has to decide something or it's the dealer's turn
+    fn advance(&mut self, events: &mut Vec<Event>) -> Result<(), EngineError> {
+        if self.table.round.is_none() {
+            return Ok(());
+        }
+
+        self.with_round(|engine, round| {
+            let rules = engine.table.rules.clone();
+
+            loop {
+                match round.phase {
+                    RoundPhase::EarlySurrender => {
+                        // Early surrender is offered before the dealer peeks, so it saves half the bet even against a blackjack
+                        let offered = rules.surrender == SurrenderRule::Early
+                            && rules.hole_card == HoleCardRule::Peek
+                            && round.dealer_hand[0].rank.value() >= 10
+                            && !round.hands[0].is_natural();
+                        if offered {
+                            return Ok(());
+                        }
+
+                        round.phase = RoundPhase::Insurance;
+                    },
+                    RoundPhase::Insurance => {
+                        // If the dealer shows an ace, the player is offered insurance, or even money if they have a blackjack
+                        let offered = round.dealer_hand[0].rank == Rank::Ace
+                            && (round.hands[0].is_natural() || !engine.max_insurance(round).is_zero());
+                        if offered {
+                            return Ok(());
+                        }
+
+                        round.phase = RoundPhase::Naturals;
+                    },
+                    RoundPhase::Naturals => engine.settle_naturals(round, events)?,
+                    RoundPhase::PlayerTurn => {
+                        // Hands are played one at a time and splitting adds a new hand after the active one
+                        let active = round.active;
+
+                        if active >= round.hands.len() {
+                            round.phase = RoundPhase::DealerTurn;
+                        } else if round.hands[active].cards.len() == 1 {
+                            // A split hand gets its second card when its turn starts
+                            engine.deal_to(round, Seat::Player(active), events)?;
+
+                            // Split aces get only one card unless the table allows hitting them
+                            if round.hands[active].is_split_aces() && !rules.hit_split_aces {
+                                round.active += 1;
+                            }
+                        } else if round.hands[active].value() >= 21 {
+                            // The hand is finished once it gets to 21 or busts
+                            round.active += 1;
+                        } else {
+                            return Ok(());
+                        }
+                    },
+                    RoundPhase::DealerTurn | RoundPhase::Finished => return Ok(())
+                };
+            }
+        })
+    }
+
+    /// Pays naturals and settles insurance if the dealer peeks. The round ends if either has a blackjack
+    fn settle_naturals(&mut self, round: &mut Round, events: &mut Vec<Event>) -> Result<(), EngineError> {
+        let bet = round.bet;
+        let dealer_blackjack = round.dealer_blackjack();
+        let peek = self.table.rules.hole_card == HoleCardRule::Peek;
+
+        // If both player and dealer get blackjack
+        if round.hands[0].is_natural() && dealer_blackjack {
+            return self.finish(round, RoundOutcome::BlackjackPush, bet, events);
+        }
+
+        // If player gets blackjack, it pays according to the table rules
+        if round.hands[0].is_natural() {
+            let payout = self.table.rules.blackjack_payout.winnings(bet)
+                .and_then(|winnings| winnings.checked_add(bet))
+                .ok_or(EngineError::Overflow)?;
+
+            return self.finish(round, RoundOutcome::Blackjack, payout, events);
+        }
+
+        // Insurance is settled when the dealer peeks
+        if peek && !round.insurance.is_zero() {
+            self.settle_insurance(round, events)?;
+        }
+
+        // If dealer peeks and has blackjack, the round ends before the player acts
+        if peek && dealer_blackjack {
+            return self.finish(round, RoundOutcome::DealerBlackjack, Money::ZERO, events);
+        }
+
+        round.phase = RoundPhase::PlayerTurn;
+
+        Ok(())
+    }
+
+    /// Pays the insurance bet if the dealer has a blackjack. Insurance pays 2:1
+    fn settle_insurance(&mut self, round: &Round, events: &mut Vec<Event>) -> Result<(), EngineError> {
+        let payout = if round.dealer_blackjack() {
+            round.insurance.checked_mul(3).ok_or(EngineError::Overflow)?
+        } else {
+            Money::ZERO
+        };
+        self.pay(payout)?;
+
+        events.push(Event::InsuranceSettled { stake: round.insurance, payout });
+
+        Ok(())
+    }
+
+    /// Checks if the table rules allow the action for the active hand
+    ///
+    /// # Returns
+    ///
+    /// Ok or an EngineError telling why the action isn't allowed
+    fn check_play(&self, round: &Round, action: Action) -> Result<(), EngineError> {
+        let rules = &self.table.rules;
+        let hand_amt = round.hands.len();
+        let hand = &round.hands[round.active];
+
+        match action {
+            Action::Hit | Action::Stand => {},
+            Action::DoubleDown => {
+                if hand.cards.len() > 2 {
+                    return Err(EngineError::DoubleAfterHit);
+                } else if hand.split && !rules.double_after_split {
+                    return Err(EngineError::DoubleAfterSplit);
+                } else if !rules.double_rule.allows(hand.value()) {
+                    return Err(EngineError::DoubleNotAllowed(rules.double_rule));
+                } else if hand.bet > self.bankroll {
+                    return Err(EngineError::InsufficientFunds);
+                }
+            },
+            Action::Split => {
+                if !hand.is_pair() {
+                    return Err(EngineError::NotAPair);
+                } else if hand_amt >= rules.max_split_hands as usize {
+                    return Err(EngineError::TooManySplits(rules.max_split_hands));
+                } else if hand.bet > self.bankroll {
+                    return Err(EngineError::InsufficientFunds);
+                }
+            },
+            Action::Surrender => {
+                if rules.surrender == SurrenderRule::NoSurrender {
+                    return Err(EngineError::SurrenderNotAllowed);
+                } else if hand_amt > 1 || hand.cards.len() > 2 {
+                    return Err(EngineError::SurrenderAfterHit);
+                }
+            },
+            Action::Insurance(_) | Action::EvenMoney | Action::Decline => return Err(EngineError::InvalidAction)
+        };
+
+        Ok(())
+    }
+
+    /// Plays the active hand if the table rules allow the action
+    fn play_hand(&mut self, round: &mut Round, action: Action, events: &mut Vec<Event>) -> Result<(), EngineError> {
+        self.check_play(round, action)?;
+
+        let hand_amt = round.hands.len();
+        let active = round.active;
+        let hand = &round.hands[active];
+
+        match action {
+            Action::Hit => self.deal_to(round, Seat::Player(active), events)?,
+            Action::Stand => round.active += 1,
+            Action::DoubleDown => {
+                // Double down allows player to only hit once with double the bet
+                let bet = hand.bet;
+                self.charge(bet)?;
+                let hand = &mut round.hands[active];
+                hand.bet = bet.checked_mul(2).ok_or(EngineError::Overflow)?;
+                hand.doubled = true;
+                events.push(Event::BetDoubled { hand: active });
+
+                self.deal_to(round, Seat::Player(active), events)?;
+                round.active += 1;
+            },
+            Action::Split => {
+                // The new hand gets a bet equal to the original one
+                let bet = hand.bet;
+                self.charge(bet)?;
+
+                // Move the second card to a new hand. Both hands get their second card when their turn starts
+                let hand = &mut round.hands[active];
+                let mut new_hand = Hand::new(bet);
+                new_hand.split = true;
+                hand.split = true;
+                if let Some(card) = hand.cards.pop() {
+                    new_hand.cards.push(card);
+                }
+                round.hands.insert(active + 1, new_hand);
+                events.push(Event::HandSplit { hand: active });
+            },
+            Action::Surrender => {
+                round.surrendered = true;
+                round.active = hand_amt;
+            },
+            Action::Insurance(_) | Action::EvenMoney | Action::Decline => return Err(EngineError::InvalidAction)
+        };
+
+        Ok(())
+    }
+
+    /// Settles every bet of the round once the dealer's hand is complete
+    fn settle(&mut self, round: &mut Round, events: &mut Vec<Event>) -> Result<(), EngineError> {
+        let rules = self.table.rules.clone();
+        let dealer_blackjack = round.dealer_blackjack();
+
+        // If dealer didn't peek, insurance is settled once the second card is revealed
+        if rules.hole_card != HoleCardRule::Peek && !round.insurance.is_zero() {
+            self.settle_insurance(round, events)?;
+        }
+
+        // If player surrendered, half of the bet is refunded. Late surrender doesn't help against a dealer
+        // blackjack that wasn't peeked for, but early surrender does
+        if round.surrendered {
+            if rules.hole_card != HoleCardRule::Peek && dealer_blackjack && rules.surrender == SurrenderRule::Late {
+                return self.finish(round, RoundOutcome::SurrenderLost, Money::ZERO, events);
+            }
+
+            let refund = round.bet.half();
+            return self.finish(round, RoundOutcome::Surrendered, refund, events);
+        }
+
+        // If every hand busted, the dealer doesn't need to play
+        if round.hands.iter().all(|hand| hand.is_bust()) {
+            return self.finish(round, RoundOutcome::Bust, Money::ZERO, events);
+        }
+
+        // If dealer didn't peek, a dealer blackjack is revealed only now. It takes every bet, including doubles
+        // and splits, unless the table only takes the original bet (OBO)
+        if rules.hole_card != HoleCardRule::Peek && dealer_blackjack {
+            let refund = if rules.original_bets_only {
+                let total_bet = round.total_bet().ok_or(EngineError::Overflow)?;
+                total_bet.checked_sub(round.bet).unwrap_or_default()
+            } else {
+                Money::ZERO
+            };
+
+            return self.finish(round, RoundOutcome::DealerBlackjack, refund, events);
+        }
+
+        // Settle every hand against the dealer
+        for (index, hand) in round.hands.iter().enumerate() {
+            let (outcome, payout) = compare_hand(hand, &round.dealer_hand)?;
+            self.pay(payout)?;
+
+            events.push(Event::HandSettled { hand: index, outcome, payout });
+        }
+        round.phase = RoundPhase::Finished;
+        events.push(Event::RoundFinished);
+
+        Ok(())
+    }
+}
+
+/// Checks if the dealer has to draw another card according to the table rules
+fn dealer_should_hit(dealer_hand: &[Card], rules: &RuleSet) -> bool {
+    let value = evaluate(dealer_hand);
+
+    value.total() < 17 || (value.total() == 17 && rules.dealer_hits_soft_17 && value.is_soft())
+}
+
+/// Compares a finished hand to the dealer's hand
+///
+/// # Returns
+///
+/// Ok containing the result and the amount paid back to the player, or an EngineError if the payout doesn't fit
+fn compare_hand(hand: &Hand, dealer_hand: &[Card]) -> Result<(HandOutcome, Money), EngineError> {
+    let dealer_value = evaluate(dealer_hand);
+    let winnings = hand.bet.checked_mul(2).ok_or(EngineError::Overflow)?;
+
+    let result = if hand.is_bust() {
+        (HandOutcome::Bust, Money::ZERO)
+    } else if dealer_value.is_bust() {
+        (HandOutcome::DealerBust, winnings)
+    } else if hand.value() == dealer_value.total() {
+        (HandOutcome::Push, hand.bet)
+    } else if hand.value() > dealer_value.total() {
+        (HandOutcome::Win, winnings)
+    } else {
+        (HandOutcome::Loss, Money::ZERO)
+    };
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::format_cards;
+    use crate::shoe::Shoe;
+
+    const BET: Money = Money::from_cents(1000);
+
+    /// Plays rounds of $10 at the default table, standing on every hand and declining every offer
+    fn play_rounds(seed: u64, rounds: usize) -> Vec<Event> {
+        let mut engine = Engine::with_seed(Money::from_cents(100_000), RuleSet::default(), seed);
+        let mut events = Vec::new();
+
+        for _ in 0..rounds {
+            events.extend(engine.bet(Money::from_cents(1000)).unwrap());
+            loop {
+                let new_events = match engine.state() {
+                    State::Betting => break,
+                    State::PlayerTurn(_) => engine.act(Action::Stand),
+                    State::DealerTurn => engine.step(),
+                    State::EarlySurrenderOffer | State::InsuranceOffer(_) | State::EvenMoneyOffer => engine.act(Action::Decline)
+                };
+                events.extend(new_events.unwrap());
+            }
+        }
+
+        events
+    }
+
+    /// Returns the cards of the first shoe dealt with the passed generator
+    fn deal_shoe(rng: &mut ChaCha8Rng) -> Vec<Card> {
+        let mut shoe = Shoe::new(6, 0.75);
+        shoe.shuffle(rng);
+        (0..50).map(|_| shoe.deal().unwrap()).collect()
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_events() {
+        assert_eq!(play_rounds(42, 30), play_rounds(42, 30));
+    }
+
+    #[test]
+    fn seed_deals_the_same_cards_on_every_release() {
+        let cards: Vec<Card> = play_rounds(42, 3)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::CardDealt { card, .. } => Some(card),
+                _ => None
+            })
+            .collect();
+
+        assert_eq!(format_cards(&cards), "J♠, 7♠, 9♦, Q♥, 9♠, 7♠, 3♥, 5♠, 10♠, 5♣, 8♥, 3♠, J♦, 10♦");
+    }
+
+    #[test]
+    fn empty_shoe_shuffles_the_discards_in_the_middle_of_a_round() {
+        let rules = RuleSet { decks: 1, penetration: 1.0, ..RuleSet::default() };
+        let mut engine = Engine::with_seed(Money::from_cents(1_000_000), rules, 7);
+        let mut discards_shuffled = false;
+
+        // Hitting to 17 uses more cards than the reserve behind the cut card now and then
+        for _ in 0..500 {
+            let mut events = engine.bet(Money::from_cents(1000)).unwrap();
+            loop {
+                let new_events = match engine.state() {
+                    State::Betting => break,
+                    State::PlayerTurn(active) if engine.round().unwrap().hands[active].evaluate().total() < 17 => engine.act(Action::Hit),
+                    State::PlayerTurn(_) => engine.act(Action::Stand),
+                    State::DealerTurn => engine.step(),
+                    State::EarlySurrenderOffer | State::InsuranceOffer(_) | State::EvenMoneyOffer => engine.act(Action::Decline)
+                };
+                events.extend(new_events.unwrap());
+            }
+            discards_shuffled |= events.contains(&Event::DiscardsShuffled);
+        }
+
+        assert!(discards_shuffled);
+    }
+
+    #[test]
+    fn shuffles_of_adjacent_seeds_differ() {
+        assert_ne!(deal_shoe(&mut shuffle_rng(5, 1)), deal_shoe(&mut shuffle_rng(6, 0)));
+        assert_ne!(deal_shoe(&mut shuffle_rng(5, 0)), deal_shoe(&mut shuffle_rng(5, 1)));
+    }
+
+    /// Creates an engine with $1000 whose shoe deals the passed cards in order, e.g. "As, Kh". The player gets the
+    /// first two cards and the dealer the next ones
+    fn stacked(rules: RuleSet, cards: &str) -> Engine {
+        let cards: Vec<Card> = cards.split(',').map(|card| card.parse().unwrap()).collect();
+        let mut table = Table::new(rules, 0);
+        table.shoe = Shoe::stacked(&cards);
+
+        Engine::resume(Money::from_cents(100_000), table).unwrap()
+    }
+
+    /// Lets the dealer play until the round is over
+    fn finish_round(engine: &mut Engine) -> Vec<Event> {
+        let mut events = Vec::new();
+        while engine.state() == State::DealerTurn {
+            events.extend(engine.step().unwrap());
+        }
+
+        events
+    }
+
+    /// Returns the result of the round if it ended as a whole
+    fn round_settled(events: &[Event]) -> Option<(RoundOutcome, Money)> {
+        events.iter().find_map(|event| match event {
+            Event::RoundSettled { outcome, payout } => Some((*outcome, *payout)),
+            _ => None
+        })
+    }
+
+    /// Returns the result of every hand compared to the dealer's
+    fn hands_settled(events: &[Event]) -> Vec<(HandOutcome, Money)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::HandSettled { outcome, payout, .. } => Some((*outcome, *payout)),
+                _ => None
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dealer_peeks_for_a_blackjack_before_the_player_acts() {
+        let mut engine = stacked(RuleSet::default(), "10s, 6h, Kd, As");
+        let events = engine.bet(BET).unwrap();
+
+        assert_eq!(round_settled(&events), Some((RoundOutcome::DealerBlackjack, Money::ZERO)));
+        assert_eq!(engine.state(), State::Betting);
+        assert_eq!(engine.bankroll(), Money::from_cents(99_000));
+    }
+
+    #[test]
+    fn blackjack_pays_by_the_table_rules() {
+        let mut engine = stacked(RuleSet::default(), "As, Kh, 9d, 7c");
+        let events = engine.bet(BET).unwrap();
+        assert_eq!(round_settled(&events), Some((RoundOutcome::Blackjack, Money::from_cents(2500))));
+
+        let mut engine = stacked(RuleSet::preset("Low limit 6:5"), "As, Kh, 9d, 7c");
+        let events = engine.bet(BET).unwrap();
+        assert_eq!(round_settled(&events), Some((RoundOutcome::Blackjack, Money::from_cents(2200))));
+
+        let mut engine = stacked(RuleSet::default(), "As, Kh, Qd, Ac");
+        let events = engine.bet(BET).unwrap();
+        assert_eq!(round_settled(&events), Some((RoundOutcome::BlackjackPush, BET)));
+    }
+
+    #[test]
+    fn even_money_pays_a_blackjack_one_to_one() {
+        let mut engine = stacked(RuleSet::default(), "As, Kh, Ad, Kc");
+        engine.bet(BET).unwrap();
+        assert_eq!(engine.state(), State::EvenMoneyOffer);
+
+        let events = engine.act(Action::EvenMoney).unwrap();
+        assert_eq!(round_settled(&events), Some((RoundOutcome::EvenMoney, Money::from_cents(2000))));
+        assert_eq!(engine.bankroll(), Money::from_cents(101_000));
+    }
+
+    #[test]
+    fn insurance_pays_two_to_one_against_a_blackjack() {
+        let mut engine = stacked(RuleSet::default(), "10s, 6h, Ad, Kc");
+        engine.bet(BET).unwrap();
+        assert_eq!(engine.state(), State::InsuranceOffer(Money::from_cents(500)));
+        assert_eq!(engine.act(Action::Insurance(Money::from_cents(600))), Err(EngineError::InvalidInsurance(Money::from_cents(500))));
+
+        let events = engine.act(Action::Insurance(Money::from_cents(500))).unwrap();
+        assert!(events.contains(&Event::InsuranceSettled { stake: Money::from_cents(500), payout: Money::from_cents(1500) }));
+        assert_eq!(round_settled(&events), Some((RoundOutcome::DealerBlackjack, Money::ZERO)));
+        assert_eq!(engine.bankroll(), Money::from_cents(100_000));
+    }
+
+    #[test]
+    fn lost_insurance_is_settled_before_the_player_acts() {
+        let mut engine = stacked(RuleSet::default(), "10s, 6h, Ad, 7c");
+        engine.bet(BET).unwrap();
+
+        let events = engine.act(Action::Insurance(Money::from_cents(500))).unwrap();
+        assert!(events.contains(&Event::InsuranceSettled { stake: Money::from_cents(500), payout: Money::ZERO }));
+        assert_eq!(engine.state(), State::PlayerTurn(0));
+    }
+
+    #[test]
+    fn dealer_blackjack_takes_doubles_and_splits_without_a_hole_card() {
+        // Both eights are split, the first hand is doubled on 11 and the dealer's ace comes after the player's turn
+        let play = |rules: RuleSet| {
+            let mut engine = stacked(rules, "8s, 8h, Kd, 3c, 5d, 2s, Ac");
+            engine.bet(BET).unwrap();
+            assert_eq!(engine.state(), State::PlayerTurn(0));
+            engine.act(Action::Split).unwrap();
+            engine.act(Action::DoubleDown).unwrap();
+            engine.act(Action::Stand).unwrap();
+
+            let events = finish_round(&mut engine);
+            (round_settled(&events), engine.bankroll())
+        };
+
+        assert_eq!(play(RuleSet::preset("European")), (Some((RoundOutcome::DealerBlackjack, Money::ZERO)), Money::from_cents(97_000)));
+
+        // Only the original bet is lost under OBO
+        assert_eq!(play(RuleSet::preset("European OBO")), (Some((RoundOutcome::DealerBlackjack, Money::from_cents(2000))), Money::from_cents(99_000)));
+    }
+
+    #[test]
+    fn late_surrender_refunds_half_the_bet() {
+        let mut engine = stacked(RuleSet::preset("Atlantic City"), "10s, 6h, 9d, 7c");
+        engine.bet(BET).unwrap();
+        engine.act(Action::Surrender).unwrap();
+
+        let events = finish_round(&mut engine);
+        assert_eq!(round_settled(&events), Some((RoundOutcome::Surrendered, Money::from_cents(500))));
+        assert_eq!(engine.bankroll(), Money::from_cents(99_500));
+    }
+
+    #[test]
+    fn late_surrender_doesnt_help_against_an_unpeeked_blackjack() {
+        let mut engine = stacked(RuleSet::preset("No peek"), "10s, 6h, Kd, As");
+        engine.bet(BET).unwrap();
+        assert_eq!(engine.state(), State::PlayerTurn(0));
+        engine.act(Action::Surrender).unwrap();
+
+        let events = finish_round(&mut engine);
+        assert_eq!(round_settled(&events), Some((RoundOutcome::SurrenderLost, Money::ZERO)));
+    }
+
+    #[test]
+    fn early_surrender_is_offered_before_the_peek() {
+        let rules = RuleSet { surrender: SurrenderRule::Early, ..RuleSet::default() };
+        let mut engine = stacked(rules, "10s, 6h, Kd, As");
+        engine.bet(BET).unwrap();
+        assert_eq!(engine.state(), State::EarlySurrenderOffer);
+
+        // Declining is reviewed like any other decision
+        assert_eq!(engine.hint(), Some(Action::Surrender));
+        let mistake = engine.review(Action::Decline).unwrap();
+        assert_eq!((mistake.played, mistake.correct), (Action::Decline, Action::Surrender));
+        assert!(mistake.cost > 0.0);
+        assert_eq!(engine.review(Action::Surrender), None);
+
+        let events = engine.act(Action::Surrender).unwrap();
+        assert_eq!(round_settled(&events), Some((RoundOutcome::Surrendered, Money::from_cents(500))));
+    }
+
+    #[test]
+    fn split_hands_are_settled_one_by_one() {
+        // The first eight gets a 3 and is doubled to 21, the second one gets a 9 and pushes the dealer's 17
+        let mut engine = stacked(RuleSet::default(), "8s, 8h, 10d, 7c, 3s, 10h, 9c");
+        engine.bet(BET).unwrap();
+        assert_eq!(engine.act(Action::Split).unwrap(), vec![
+            Event::HandSplit { hand: 0 },
+            Event::CardDealt { seat: Seat::Player(0), card: "3s".parse().unwrap() }
+        ]);
+        engine.act(Action::DoubleDown).unwrap();
+        assert_eq!(engine.state(), State::PlayerTurn(1));
+        engine.act(Action::Stand).unwrap();
+
+        let events = finish_round(&mut engine);
+        assert_eq!(hands_settled(&events), vec![(HandOutcome::Win, Money::from_cents(4000)), (HandOutcome::Push, BET)]);
+        assert_eq!(engine.bankroll(), Money::from_cents(102_000));
+    }
+
+    #[test]
+    fn early_surrender_without_a_peek_follows_the_expected_values() {
+        let rules = RuleSet::preset("European OBO");
+
+        for cards in ["10s, 6h, Kd", "10s, 6h, 9d", "10s, 5h, Kd", "10s, 4h, Ad", "8s, 8h, Ad", "10s, 7h, Ad", "9s, 7h, 7d"] {
+            let mut engine = stacked(rules.clone(), cards);
+            engine.bet(BET).unwrap();
+            if matches!(engine.state(), State::InsuranceOffer(_)) {
+                engine.act(Action::Decline).unwrap();
+            }
+            assert_eq!(engine.state(), State::PlayerTurn(0), "{cards}");
+
+            let round = engine.round().unwrap();
+            let (hand, upcard) = (&round.hands[0].cards, round.dealer_hand[0].rank);
+            let value = |action| expected_value(hand, upcard, &rules, action).unwrap();
+            let hint = engine.hint().unwrap();
+
+            // Surrendering is the hint exactly when it's worth more than the play basic strategy makes otherwise
+            let played_on = basic_strategy(hand, upcard, &rules, Options { double: true, split: true, surrender: false });
+            assert_eq!(hint == Action::Surrender, value(Action::Surrender) > value(played_on), "{cards}");
+
+            for action in [Action::Hit, Action::Stand, Action::Surrender] {
+                let cost = engine.review(action).map_or(0.0, |mistake| mistake.cost);
+                assert!((cost - (value(hint) - value(action)).max(0.0)).abs() < 1e-9, "{cards}: {action:?}");
+            }
+        }
+    }
+}
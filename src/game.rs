@@ -1,169 +1,230 @@
-use whoami::fallible::realname;
-
-use std::io::Write;
-use std::thread::sleep;
 use std::time::Duration;
-use std::{io, u8};
-
-use rand::seq::SliceRandom;
-use rand::thread_rng;
 
-use crate::input;
+use blackjack::card::{evaluate, format_cards};
+use blackjack::engine::{Action, Engine, Event, HandOutcome, RoundOutcome, State};
+use blackjack::hand::Hand;
+use blackjack::money::Money;
+use blackjack::rules::{HoleCardRule, RuleSet};
+use blackjack::strategy::HandKind;
+
+use crate::history;
+use crate::input::{self, BetCommand, GameCommand, InputSource};
+use crate::profiles::ask_player_name;
+use crate::review::{end_session, record_decision, start_session};
 use crate::save::{save, load, Player};
-use crate::utils::{clear_terminal, notification, NotificationDuration};
+use crate::settings::{counting_system, settings_menu, show_count, toggle_count};
+use crate::utils::{clear_terminal, notification, pause, NotificationDuration};
 
-// Create constant arrays for card icons and numbers
-const SUIT_ARRAY: [char; 4] = ['♠', '♣', '♥', '♦'];
-const NUM_ARRAY: [&str; 13] = [
-    "2", "3", "4", "5", "6", "7", "8", "9", "10", "J", "Q", "K", "A",
-];
+/// Formats the value of a player's hand, e.g. "7/17", "Blackjack" or "25, bust"
+fn describe_value(hand: &Hand) -> String {
+    let value = hand.evaluate();
 
-/// Creates a sorted list of cards
-///
-/// # Returns
+    if hand.is_natural() {
+        String::from("Blackjack")
+    } else if value.is_bust() {
+        format!("{value}, bust")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Prints the table: dealer's cards, every player hand and the options for the active hand
 ///
-/// Vec of strings
-fn create_deck_vec() -> Vec<String> {
-    // Create new deck vector
-    let mut deck_vec: Vec<String> = Vec::new();
+/// The terminal is redrawn completely so that any amount of split hands fits on the screen
+fn print_game_state(engine: &Engine, active: Option<usize>, dealer_turn: bool) {
+    let Some(round) = engine.round() else {
+        return;
+    };
+    clear_terminal();
+
+    let hands = &round.hands;
+    let dealer_hand = &round.dealer_hand;
+
+    // Total amount of money on the table
+    let total_bet = round.total_bet().unwrap_or_default();
+
+    if dealer_turn {
+        println!("--- DEALER'S TURN | BET: ${total_bet} ---");
+        println!("Dealer's cards: [{}] ({})", format_cards(dealer_hand), evaluate(dealer_hand));
+    } else if dealer_hand.len() == 1 {
+        // Without a hole card only the upcard is on the table
+        println!("--- YOUR TURN | BET: ${total_bet} ---");
+        println!("Dealer's cards: [{}] ({})", dealer_hand[0], evaluate(dealer_hand));
+    } else {
+        println!("--- YOUR TURN | BET: ${total_bet} ---");
+        println!("Dealer's cards: [{}, ??] (??)", dealer_hand[0]);
+    }
+
+    // Print every player hand and mark the one being played
+    for (index, hand) in hands.iter().enumerate() {
+        let marker = if active == Some(index) { ">" } else { " " };
+
+        if hands.len() == 1 {
+            println!("{marker} Your cards: [{}] ({})", format_cards(&hand.cards), describe_value(hand));
+        } else {
+            println!("{marker} Hand {}: [{}] ({}) | BET: ${}", index + 1, format_cards(&hand.cards), describe_value(hand), hand.bet);
+        }
+    }
+
+    if show_count() {
+        println!("{}", describe_count(engine));
+    }
+
+    // Options are only shown while the player has a hand to play
+    if active.is_some() {
+        println!("\n---");
+        println!("What do you want to do?");
+        println!("1. Hit (h)");
+        println!("2. Stand (s)");
+        println!("3. Double down (d)");
+        println!("4. Split (p)");
+        println!("5. Surrender (r)");
+    }
+    pause(Duration::from_secs(1));
+}
 
-    // Add one card of each type to the vector
-    for suit in SUIT_ARRAY {
-        for num in NUM_ARRAY {
+/// Prints every command that can be typed during a hand
+fn print_help() {
+    println!("Commands:");
+    println!("  h, hit or 1        Take another card");
+    println!("  s, stand or 2      Keep the hand as it is");
+    println!("  d, double or 3     Double the bet and take exactly one more card");
+    println!("  p, split or 4      Split a pair into two hands");
+    println!("  r, surrender or 5  Give up the hand and get half of the bet back");
+    println!("  i, insurance or 6  Take insurance or even money when it's offered");
+    println!("  n, decline or 7    Decline an offer");
+    println!("  hint               Show what basic strategy would do");
+    println!("  count              Show or hide the running count and the true count");
+    println!("  q, pause           Open the pause menu to see your stats, change settings or quit");
+    println!("  ?, help            Show this list");
+}
 
-            // Fuse card number and suit together
-            let mut card = String::from(num);
-            card.push(suit);
+/// What the player answered at a prompt
+enum Reply<T> {
+    Answer(T),
+    /// Player wants to open the pause menu instead of answering
+    Pause
+}
 
-            // Push card String to deck vec
-            deck_vec.push(card);
+impl<T> Reply<T> {
+    /// Converts the answer, keeping a pause as it is
+    fn map<U>(self, f: impl FnOnce(T) -> U) -> Reply<U> {
+        match self {
+            Reply::Answer(answer) => Reply::Answer(f(answer)),
+            Reply::Pause => Reply::Pause
         }
     }
+}
 
-    return deck_vec;
+/// Something the player does at the table
+enum Move {
+    Bet(Money),
+    Act(Action),
+    /// Let the dealer play
+    Step
 }
 
-/// Shuffles passed deck of cards
-fn shuffle_deck(deck_vec: &mut Vec<String>) {
-    deck_vec.shuffle(&mut thread_rng());
+/// Formats the count in the chosen counting system, e.g. "Hi-Lo: Running count +3 | True count +1.5 | 2.0 decks left"
+fn describe_count(engine: &Engine) -> String {
+    let system = counting_system();
+    format!("{}: {}", system.name, engine.count(&system))
+}
 
-    println!("Shuffling...");
-    sleep(Duration::from_secs(2));
+/// Shows the count if it's hidden or hides it if it's shown
+fn toggle_count_display(engine: &Engine) {
+    if toggle_count() {
+        println!("{}", describe_count(engine));
+    } else {
+        notification("The count is hidden", NotificationDuration::Short);
+    }
 }
 
-/// Calculates the total value of a vec of cards
+/// Reads commands until the player chooses an option. Help, the hint and the count are printed right away
 ///
 /// # Returns
 ///
-/// u8
-fn cards_value(hand_vec: &Vec<String>) -> u8 {
-    // Create variables for sum of card values and amount of aces
-    let mut total: u8 = 0;
-    let mut aces_amt: u8 = 0;
-
-    // Go through each card in the hand and add up their values
-    for card in hand_vec {
-
-        // Create clone of card to assume ownership
-        let mut card_value = card.clone();
-
-        // Pop out the suit from the card so that only the value can be read
-        match card_value.pop() {
-            Some(_) => {},
-            None    => { println!("Cannot pop card suit because the card is empty"); }
+/// Chosen option, a pause or None if the input ended
+fn read_option(engine: &Engine, input: &mut dyn InputSource) -> Option<Reply<input::InGameOptions>> {
+    loop {
+        match input.read_line()?.parse::<GameCommand>() {
+            Ok(GameCommand::Play(option)) => return Some(Reply::Answer(option)),
+            Ok(GameCommand::Help) => print_help(),
+            Ok(GameCommand::Hint) => match describe_hint(engine) {
+                Some(hint) => println!("{hint}"),
+                None => notification("There's no hint for this decision", NotificationDuration::Short)
+            },
+            Ok(GameCommand::Count) => toggle_count_display(engine),
+            Ok(GameCommand::Pause) => return Some(Reply::Pause),
+            Err(err) => notification(&err, NotificationDuration::Short)
         };
-
-        // Add card value to the total according to their number
-        if card_value == "A" {
-            total = total.saturating_add(11);
-            aces_amt += 1;
-        } else if ["10", "J", "Q", "K"].contains(&card_value.as_str()) {
-            total = total.saturating_add(10);
-        } else {
-            // Parse integer from card num string
-            let value: u8 = match card_value.parse() {
-                Ok(num) => num,
-                Err(_)      => { println!("Cannot parse integer from card value because it is unknown"); continue; }
-            };
-            total = total.saturating_add(value);
-        }
     }
-    // If there are aces in the hand and the total value exceeds 21, the aces' value changes from 11 to 1
-    while total > 21 && aces_amt > 0 {
-        total = total.saturating_sub(10);
-        aces_amt -= 1;
-    }
-
-    return total;
 }
 
-/// Deals a card to a hand vec from the deck vec
-/// 
+/// Asks the player if they want to take an offer, e.g. insurance, or decline it
+///
 /// # Returns
-/// 
-/// Ok or an Err if the deck is empty
-fn deal_cards(to_vec: &mut Vec<String>, from_vec: &mut Vec<String>, amt_cards: u8) -> Result<(), String> {
-    for _ in 1..=amt_cards {
-
-        // Check if deck is empty
-        if from_vec.is_empty() {
-            return Err(String::from("Given deck is empty, cannot deal cards"));
-        }
-
-        // Pop the card from the deck
-        let dealt_card = match from_vec.pop() {
-            Some(card) => card,
-            None               => {
-                // If for some reason the deck is still empty despite the earlier check, return an Err
-                return Err(String::from("Deck was unexpectedly emptied while dealing")) 
-            }
+///
+/// true if the player took the offer, a pause or None if the input ended
+fn ask_offer(engine: &Engine, question: &str, option: input::InGameOptions, option_text: &str, decline_text: &str, input: &mut dyn InputSource) -> Option<Reply<bool>> {
+    let decline = input::InGameOptions::Decline;
+
+    println!("\n---");
+    println!("{question}");
+    println!("{}. {option_text} ({})", option as u8, option.shortcut());
+    println!("{}. {decline_text} ({})", decline as u8, decline.shortcut());
+
+    loop {
+        let Reply::Answer(chosen) = read_option(engine, input)? else {
+            return Some(Reply::Pause);
         };
 
-        to_vec.push(dealt_card);
+        if chosen == option {
+            return Some(Reply::Answer(true));
+        } else if chosen == decline {
+            return Some(Reply::Answer(false));
+        }
+        notification(&format!("Choose {} or {} first", option.shortcut(), decline.shortcut()), NotificationDuration::Short);
     }
-    Ok(())
 }
 
-/// Prints the current hands and bet
-fn print_game_state(player_hand: &Vec<String>, dealer_hand: &Vec<String>, dealer_turn: bool) {
-    // Calculate total values for both player's and dealer's hands
-    let player_hand_value: u8 = cards_value(&player_hand);
-    let dealer_hand_value: u8 = cards_value(&dealer_hand);
-
-    if dealer_turn {
-        print!("\r\x1B[6A\x1B[KYour cards: [{}] ({player_hand_value})\tDealer's cards: [{}] ({dealer_hand_value})\r\x1B[6B", player_hand.join(", "), dealer_hand.join(", "));
-        io::stdout().flush().unwrap();
-    } else {
-        print!("\r\x1B[6A\x1B[KYour cards: [{}] ({player_hand_value})\tDealer's cards: [{}, ??] (??)\x1B[6B\r", player_hand.join(", "), dealer_hand[0]);
-        io::stdout().flush().unwrap();
+/// Asks how much insurance the player wants to buy
+///
+/// # Returns
+///
+/// Insurance bet greater than 0 and at most the passed maximum or None if the input ended
+fn ask_insurance_amount(max_insurance: Money, input: &mut dyn InputSource) -> Option<Money> {
+    println!("How much insurance do you want? You can buy up to ${max_insurance}");
+
+    loop {
+        // Get user input
+        let line = input.read_line()?;
+
+        // Check if input is valid
+        match line.parse::<Money>() {
+            Ok(amount) if !amount.is_zero() && amount <= max_insurance => return Some(amount),
+            _ => {
+                notification(&format!("Input an amount greater than 0 and at most {max_insurance}"), NotificationDuration::Short);
+            }
+        };
     }
-    sleep(Duration::from_secs(1));
 }
 
-/// Starts a game with new stats
-pub fn new_game() {
-    // Create user name variable
-    let user_name: String;
-
-    // Get user name from user's PC
-    match realname() {
-        Ok(name) => user_name = name,
-        Err(_) => {
-            user_name = String::from("User");
-        }
-    };
+/// Starts a game with a new profile. The table shuffles from the passed seed if there is one
+pub fn new_game(rules: &RuleSet, seed: Option<u64>, input: &mut dyn InputSource) {
+    clear_terminal();
 
-    // Create new player with the user name
-    let mut player: Player = Player {
-        name: String::from(user_name),
-        wealth: 10,
+    // Create new player with the name the user chose
+    let Some(name) = ask_player_name(input) else {
+        return;
     };
+    let mut player = Player::new(name);
+    start_session(&mut player);
 
     clear_terminal();
 
     // Create new save file
-    match save(&player) {
-        Ok(_) => { println!("Created new save as {}", player.name); },
+    match save(&mut player) {
+        Ok(_) => { println!("Created new profile {}", player.name); },
         Err(_) => { 
             notification("An error occurred when saving", NotificationDuration::Long);
 
@@ -172,7 +233,7 @@ pub fn new_game() {
     };
 
     // Start new game loop
-    match game(&mut player) {
+    match game(&mut player, rules, seed, input) {
         Ok(_) => {
             clear_terminal();
         },
@@ -183,318 +244,575 @@ pub fn new_game() {
     };
 
     // Save player to the file again after loop ends
-    match save(&player) {
+    match save(&mut player) {
         Ok(_) => { notification("Saved", NotificationDuration::Short); },
-            Err(_) => { 
-                notification("An error occurred when saving", NotificationDuration::Long);
-
-                return;
-            }
+        Err(_) => {
+            notification("An error occurred when saving", NotificationDuration::Long);
+        }
     };
 }
 
-pub fn load_game() {
+/// Continues the game of the profile with the passed id. A new table shuffles from the passed seed if there is one
+pub fn load_game(id: &str, rules: &RuleSet, seed: Option<u64>, input: &mut dyn InputSource) {
 
     clear_terminal();
 
     // Get player object from file
-    let mut player = match load() {
-        Ok(player) => { println!("Loaded save file created by {}", player.name); player },
+    let mut player = match load(id) {
+        Ok(player) => { println!("Loaded profile {}", player.name); player },
         Err(err) => {
-            notification(&format!("{}", err), NotificationDuration::Long);
+            notification(&err.to_string(), NotificationDuration::Long);
 
             return;
         } 
     };
 
     // Start game loop
-    match game(&mut player) {
+    match game(&mut player, rules, seed, input) {
         Ok(_) => {
             clear_terminal();
         },
         Err(err) => {
-            notification(&format!("{}", err), NotificationDuration::Long);
+            notification(&err, NotificationDuration::Long);
             return;
         }
     };
 
     // Save player to the file again after loop ends
-    match save(&player) {
+    match save(&mut player) {
         Ok(_) => { notification("Saved", NotificationDuration::Short); },
-        Err(_) => { 
+        Err(_) => {
             notification("An error occurred when saving", NotificationDuration::Long);
-            return;
         }
     };
 }
 
-/// Main game loop
-fn game(player: &mut Player) -> Result<(), String> {
-    // Create new deck and shuffle it
-    let mut deck = create_deck_vec();
-    shuffle_deck(&mut deck);
-
-    while player.wealth > 0 && player.wealth < u16::MAX {
-        match save(&player) {
-            Ok(_) => { notification("Saved", NotificationDuration::Short); },
-            Err(_) => { 
-                return Err(String::from("An error occurred while saving. Returning to main menu..."));
-            }
-        };
+/// Saves the player together with the table, so that the game continues from this exact point
+///
+/// # Returns
+///
+/// Ok or an Err if saving failed
+fn save_progress(player: &mut Player, engine: &Engine) -> Result<(), String> {
+    player.wealth = engine.bankroll();
+    player.table = Some(engine.table().clone());
 
-        println!("\n---");
-        println!("You have ${}", player.wealth);
-        println!("Place your bet");
+    save(player).map_err(|_| String::from("An error occurred while saving. Returning to main menu..."))
+}
 
-        // Create variable for bet
-        let mut bet: u16;
+/// Asks the player how much they want to bet
+///
+/// # Returns
+///
+/// Bet that fits the table limits and the player's wealth, a pause or None if the input ended
+fn ask_bet(engine: &Engine, last_bet: Option<Money>, input: &mut dyn InputSource) -> Option<Reply<Money>> {
+    let wealth = engine.bankroll();
+    let limits = &engine.rules().limits;
+
+    println!("\n---");
+    println!("You have ${wealth} | {limits}");
+    if show_count() {
+        println!("{}", describe_count(engine));
+    }
+    match last_bet {
+        Some(last_bet) => println!("Place your bet. Your last bet was ${last_bet}. Type ? for help"),
+        None => println!("Place your bet. Type ? for help")
+    };
 
-        loop {
-            // Get user input
-            let mut input = String::new();
-            io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line");
+    loop {
+        // Get user input
+        let command = match input.read_line()?.parse::<BetCommand>() {
+            Ok(command) => command,
+            Err(err) => {
+                notification(&err, NotificationDuration::Short);
 
-            // Clear input to prevent bugs
-            print!("\x1B[A\r\x1B[K");
-            io::stdout().flush().unwrap();
+                continue;
+            }
+        };
 
-            // Check if input is valid
-            bet = match input.trim().parse() {
-                Ok(num) => num,
-                Err(_) => {
-                    notification("Input a whole number greater than 0", NotificationDuration::Short);
+        // Shortcuts are worked out from the last bet and rounded down to the steps of the table
+        let bet = match (command, last_bet) {
+            (BetCommand::Amount(amount), _) => amount,
+            (BetCommand::Max, _) => limits.max_bet(wealth),
+            (BetCommand::Rebet, Some(last_bet)) => last_bet,
+            (BetCommand::Double, Some(last_bet)) => last_bet.checked_mul(2).unwrap_or(wealth),
+            (BetCommand::Half, Some(last_bet)) => limits.round_down(last_bet.half()),
+            (BetCommand::Rebet | BetCommand::Double | BetCommand::Half, None) => {
+                notification("There is no last bet yet. Input an amount, e.g. 10 or 2.50", NotificationDuration::Short);
 
-                    continue;
-                }
-            };
-            if bet > player.wealth {
-                notification("You don't have that much money", NotificationDuration::Short);
+                continue;
+            },
+            (BetCommand::Help, _) => {
+                println!("Input an amount, e.g. 10 or 2.50, or one of these:");
+                println!("  rebet  Bet the same as last time");
+                println!("  2x     Bet twice as much as last time");
+                println!("  half   Bet half as much as last time");
+                println!("  max    Bet as much as the table and your money allow");
+                println!("  count  Show or hide the running count and the true count");
+                println!("  q      Open the pause menu");
 
                 continue;
-            }
+            },
+            (BetCommand::Count, _) => {
+                toggle_count_display(engine);
 
-            break;
+                continue;
+            },
+            (BetCommand::Pause, _) => return Some(Reply::Pause)
+        };
+        if let Err(err) = engine.check_bet(bet) {
+            notification(&err.to_string(), NotificationDuration::Short);
+
+            continue;
         }
 
-        println!("You are betting ${bet}");
+        return Some(Reply::Answer(bet));
+    }
+}
 
-        // Remove bet from player's wealth
-        player.wealth = player.wealth.saturating_sub(bet);
+/// Asks what the player wants to do with the active hand
+///
+/// # Returns
+///
+/// Action for the engine, a pause or None if the input ended
+fn ask_action(engine: &Engine, input: &mut dyn InputSource) -> Option<Reply<Action>> {
+    loop {
+        let Reply::Answer(option) = read_option(engine, input)? else {
+            return Some(Reply::Pause);
+        };
 
-        // Shuffle deck if less than half of cards are left
-        if deck.len() < 26 {
-            deck = create_deck_vec();
-            shuffle_deck(&mut deck);
-        }
+        // Insurance can only be bought before the player's turn
+        let Some(action) = option.play() else {
+            notification("Insurance is only offered when the dealer shows an ace", NotificationDuration::Short);
+            continue;
+        };
 
-        // Create empty hand vec for player and dealer
-        let mut player_hand: Vec<String> = Vec::new();
-        let mut dealer_hand: Vec<String> = Vec::new();
+        return Some(Reply::Answer(action));
+    }
+}
 
-        // Deal cards to both
-        deal_cards(&mut player_hand, &mut deck, 2)?;
-        deal_cards(&mut dealer_hand, &mut deck, 2)?;
+/// Returns the name of a play as it's shown in the options
+pub fn action_name(action: Action) -> &'static str {
+    match action {
+        Action::Hit => "Hit",
+        Action::Stand => "Stand",
+        Action::DoubleDown => "Double down",
+        Action::Split => "Split",
+        Action::Surrender => "Surrender",
+        Action::Insurance(_) => "Insurance",
+        Action::EvenMoney => "Even money",
+        Action::Decline => "Decline"
+    }
+}
 
-        // Print game state before player's turn starts to show cards in case of blackjack
-        println!("\n--- YOUR TURN | BET: ${bet} ---");
-        println!("\n---");
-        println!("What do you want to do?");
-        println!("1. Hit");
-        println!("2. Stand");
-        println!("3. Double down");
+/// Explains the basic strategy play for the active hand or offer, e.g. "Basic strategy for hard 11 against the dealer's 6: Double down"
+fn describe_hint(engine: &Engine) -> Option<String> {
+    let action = engine.hint()?;
+    let round = engine.round()?;
+    let upcard = round.dealer_hand.first()?;
 
-        print_game_state(&player_hand, &dealer_hand, false);
+    match engine.state() {
+        State::PlayerTurn(active) => {
+            let kind = HandKind::of(&round.hands[active].cards);
+            Some(format!("Basic strategy for {kind} against the dealer's {}: {}", upcard.rank, action_name(action)))
+        },
+        _ => Some(format!("Basic strategy against the dealer's {}: {}", upcard.rank, action_name(action)))
+    }
+}
+
+/// Returns the title and the explanation of a result that ended the whole round
+fn describe_round_outcome(outcome: RoundOutcome, payout: Money, rules: &RuleSet) -> (&'static str, String) {
+    match outcome {
+        RoundOutcome::BlackjackPush => ("DRAW", format!("You and dealer both got a blackjack. You get ${payout} back")),
+        RoundOutcome::Blackjack => ("YOU WON", format!("You got a blackjack. Won ${payout}")),
+        RoundOutcome::EvenMoney => ("YOU WON", format!("You took even money. Won ${payout}")),
+        RoundOutcome::Surrendered => ("YOU SURRENDERED", format!("You get ${payout} back")),
+        RoundOutcome::SurrenderLost => ("YOU LOST", String::from("Dealer got a blackjack. Late surrender doesn't give anything back against it")),
+        // Without doubles or splits there is nothing to give back
+        RoundOutcome::DealerBlackjack if rules.original_bets_only && rules.hole_card != HoleCardRule::Peek && !payout.is_zero() => {
+            ("YOU LOST", format!("Dealer got a blackjack. Only the original bet is lost, you get ${payout} back"))
+        },
+        RoundOutcome::DealerBlackjack => ("YOU LOST", String::from("Dealer got a blackjack")),
+        RoundOutcome::Bust => ("YOU LOST", String::from("You busted"))
+    }
+}
+
+/// Returns the title and the explanation of a hand compared to the dealer's hand
+fn describe_hand_outcome(outcome: HandOutcome, payout: Money) -> (&'static str, String) {
+    match outcome {
+        HandOutcome::Bust => ("YOU LOST", String::from("You busted")),
+        HandOutcome::DealerBust => ("YOU WON", format!("Dealer busted. You won ${payout}")),
+        HandOutcome::Push => ("DRAW", format!("You and dealer got hands of same value. You get ${payout} back")),
+        HandOutcome::Win => ("YOU WON", format!("You were closer to 21. You won ${payout}")),
+        HandOutcome::Loss => ("YOU LOST", String::from("Dealer was closer to 21."))
+    }
+}
+
+/// Shows the player what happened at the table
+///
+/// The insurance result is kept in `insurance_result` until the round is over, so it's printed with the other results
+///
+/// # Returns
+///
+/// Results of the round that were shown, for the hand history
+fn show_events(engine: &Engine, events: &[Event], insurance_result: &mut Option<String>) -> Vec<String> {
+    let mut results = Vec::new();
+
+    for event in events {
+        match *event {
+            Event::DiscardsShuffled => {
+                println!("The shoe ran out. Shuffling the discards...");
+                pause(Duration::from_secs(2));
+            },
+            Event::ShuffleStarted { decks } => {
+                println!("Shuffling {decks} decks...");
+                if show_count() {
+                    println!("The count starts again from {:+}", counting_system().initial_running_count(decks));
+                }
+                pause(Duration::from_secs(2));
+            },
+            Event::InsuranceSettled { stake, payout } => {
+                if payout.is_zero() {
+                    let result = format!("Dealer doesn't have a blackjack. You lost ${stake} insurance");
+
+                    // When the dealer peeks, the round goes on after losing insurance, so the result is only shown for a moment
+                    if engine.rules().hole_card == HoleCardRule::Peek {
+                        notification(&result, NotificationDuration::Long);
+                    } else {
+                        *insurance_result = Some(result);
+                    }
+                } else {
+                    *insurance_result = Some(format!("Insurance pays 2:1. You get ${payout} back"));
+                }
+            },
+            Event::RoundSettled { outcome, payout } => {
+                print_game_state(engine, None, true);
+
+                let (title, reason) = describe_round_outcome(outcome, payout, engine.rules());
+                println!("\n--- {title} ---");
+                println!("{reason}");
+                results.push(format!("{title}: {reason}"));
+            },
+            Event::HandSettled { hand, outcome, payout } => {
+                let (title, reason) = describe_hand_outcome(outcome, payout);
+
+                if engine.round().is_some_and(|round| round.hands.len() > 1) {
+                    println!("\n--- HAND {}: {title} ---", hand + 1);
+                    results.push(format!("Hand {}: {title}: {reason}", hand + 1));
+                } else {
+                    println!("\n--- {title} ---");
+                    results.push(format!("{title}: {reason}"));
+                }
+                println!("{reason}");
+            },
+            Event::RoundFinished => {
+                if let Some(result) = insurance_result.take() {
+                    println!("{result}");
+                    results.push(result);
+                }
+            },
+            Event::CardDealt { .. } | Event::HandSplit { .. } | Event::BetDoubled { .. } => {}
+        };
+    }
 
-        // If both player and dealer get blackjack
-        if cards_value(&player_hand) == 21 && cards_value(&dealer_hand) == 21 {
-            player.wealth = player.wealth.saturating_add(bet);
+    results
+}
 
-            print_game_state(&player_hand, &dealer_hand, true);
+/// Results of the rounds played since the game was started or continued
+struct SessionStats {
+    start_wealth: Money,
+    rounds: u32,
+    hands_won: u32,
+    hands_lost: u32,
+    hands_pushed: u32
+}
 
-            println!("\n--- DRAW ---");
-            println!("You and dealer both got a blackjack. You get {bet}$ back");
+impl SessionStats {
+    fn new(start_wealth: Money) -> SessionStats {
+        SessionStats { start_wealth, rounds: 0, hands_won: 0, hands_lost: 0, hands_pushed: 0 }
+    }
 
-            continue;
+    /// Counts the settled hands and finished rounds among the events
+    fn record(&mut self, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::RoundSettled { outcome, .. } => match outcome {
+                    RoundOutcome::Blackjack | RoundOutcome::EvenMoney => self.hands_won += 1,
+                    RoundOutcome::BlackjackPush => self.hands_pushed += 1,
+                    RoundOutcome::Surrendered | RoundOutcome::SurrenderLost | RoundOutcome::DealerBlackjack | RoundOutcome::Bust => self.hands_lost += 1
+                },
+                Event::HandSettled { outcome, .. } => match outcome {
+                    HandOutcome::Win | HandOutcome::DealerBust => self.hands_won += 1,
+                    HandOutcome::Push => self.hands_pushed += 1,
+                    HandOutcome::Bust | HandOutcome::Loss => self.hands_lost += 1
+                },
+                Event::RoundFinished => self.rounds += 1,
+                _ => {}
+            };
         }
+    }
+}
 
-        // If player gets blackjack
-        if cards_value(&player_hand) == 21 {
-            player.wealth = player.wealth.saturating_add(bet * 2);
+/// Prints the bankroll, the table and the results of this session
+fn print_stats(engine: &Engine, stats: &SessionStats) {
+    let bankroll = engine.bankroll();
+    let net = if bankroll >= stats.start_wealth {
+        format!("+${}", bankroll.checked_sub(stats.start_wealth).unwrap_or_default())
+    } else {
+        format!("-${}", stats.start_wealth.checked_sub(bankroll).unwrap_or_default())
+    };
 
-            print_game_state(&player_hand, &dealer_hand, true);
+    println!("---");
+    println!("Stats");
+    println!("Bankroll: ${bankroll} ({net} this session)");
+    println!("Rounds played this session: {}", stats.rounds);
+    println!("Hands won: {} | lost: {} | pushed: {}", stats.hands_won, stats.hands_lost, stats.hands_pushed);
+    println!("Table rules: {}", engine.rules());
+    println!("Seed: {} | Shoe: {}", engine.seed(), engine.table().shuffles);
+}
 
-            println!("\n--- YOU WON ---");
-            println!("You got a blackjack. Won {}$", bet * 2);
+/// Menu that can be opened at any prompt of the game
+///
+/// # Returns
+///
+/// true if the player resumes the game or false if they want to go back to the main menu or the input ended
+fn pause_menu(engine: &Engine, stats: &SessionStats, input: &mut dyn InputSource) -> bool {
+    loop {
+        println!("\n---");
+        println!("Paused");
+        println!("1. Resume");
+        println!("2. Stats");
+        println!("3. Settings");
+        println!("4. Save and return to main menu");
+
+        let Some(line) = input.read_line() else {
+            return false;
+        };
 
-            continue;
-        }
+        match line.parse::<input::PauseMenuOptions>() {
+            Ok(input::PauseMenuOptions::Resume) => return true,
+            Ok(input::PauseMenuOptions::Stats) => print_stats(engine, stats),
+            Ok(input::PauseMenuOptions::Settings) => settings_menu(input),
+            Ok(input::PauseMenuOptions::SaveAndQuit) => return false,
+            Err(err) => notification(&err, NotificationDuration::Short)
+        };
+    }
+}
 
-        // If dealer gets blackjack
-        if cards_value(&dealer_hand) == 21 {
-            print_game_state(&player_hand, &dealer_hand, true);
+/// Creates an engine at a new table. The shoe is shuffled from the passed seed, or a random one
+fn new_engine(bankroll: Money, rules: &RuleSet, seed: Option<u64>) -> Engine {
+    match seed {
+        Some(seed) => Engine::with_seed(bankroll, rules.clone(), seed),
+        None => Engine::new(bankroll, rules.clone())
+    }
+}
 
-            println!("\n--- YOU LOST ---");
-            println!("Dealer got a blackjack");
+/// Main game loop. It ends when the player can't cover the table minimum, returns to the main menu from the pause menu or the input ends. Everything is saved by then
+fn game(player: &mut Player, rules: &RuleSet, seed: Option<u64>, input: &mut dyn InputSource) -> Result<(), String> {
+    // Sit back at the table the player left or at a new one. The player keeps it until the game saves a newer one
+    let mut engine = match player.table.clone() {
+        Some(table) => Engine::resume(player.wealth, table).map_err(|err| err.to_string())?,
+        None => new_engine(player.wealth, rules, seed)
+    };
 
-            continue;
-        }
+    // A round that was left unfinished is played to the end with the rules and the shoe it was started with
+    if engine.round_in_progress() {
+        println!("Continuing the unfinished round. Table rules: {}", engine.rules());
+        pause(Duration::from_secs(2));
+    } else if engine.rules() == rules {
+        println!("Table rules: {rules}");
+        println!("Seed: {}", engine.seed());
+    }
 
-        // Player's turn
-        let mut index: u8 = 0;
-        'player_turn: while cards_value(&player_hand) <= 21 {
-
-            // Add to index
-            index += 1;
-
-            loop {
-                // Get user input
-                let mut input = String::new();
-                io::stdin()
-                    .read_line(&mut input)
-                    .expect("Failed to read line");
-
-                // Clear input to prevent bugs
-                print!("\x1B[A\r\x1B[K");
-                io::stdout().flush().unwrap();
-
-                // Get GameOption from input if it's valid
-                let option = match input.trim().parse::<u8>() {
-                    Ok(num) => match input::InGameOptions::try_from(num) {
-                        Ok(option) => option,
-                        Err(err) => {
-                            notification(&format!("{}", err), NotificationDuration::Short);
-                            continue;
-                        }
-                    },
-                    Err(_) => {
-                        notification("Input must be a number", NotificationDuration::Short);
-                        continue;
-                    }
-                };
-
-                // Handle different options
-                match option {
-                    // If player hits
-                    input::InGameOptions::Hit => {
-                        deal_cards(&mut player_hand, &mut deck, 1)?;
-
-                        // Print game state
-                        print_game_state(&player_hand, &dealer_hand, false);
-
-                        break;
-                    },
-                    // If player stands
-                    input::InGameOptions::Stand => {
-                        break 'player_turn;
-                    },
-                    // If player doubles down
-                    input::InGameOptions::DoubleDown => {
-                        if index > 1 {
-                            notification("You can't double down after hitting", NotificationDuration::Short);
-    
-                            continue;
-                        } else if player.wealth < bet {
-                            notification("You don't have enough money to double down", NotificationDuration::Short);
-    
-                            continue;
-                        } else {
-                            // Double down allows player to only hit once with double the bet
-                            // Reduce bet again from player's wealth to compensate for doubled bet
-                            player.wealth = player.wealth.saturating_sub(bet);
-                            bet *= 2;
-
-                            deal_cards(&mut player_hand, &mut deck, 1)?;
-
-                            // Print game state
-                            print_game_state(&player_hand, &dealer_hand, false);
-
-                            break 'player_turn;
-                        }
-                    },
-                };
+    // Result of the insurance bet waiting to be shown at the end of the round
+    let mut insurance_result: Option<String> = None;
+    let mut stats = SessionStats::new(engine.bankroll());
+
+    loop {
+        let reply = match engine.state() {
+            State::Betting => {
+                // The shoe is only kept if the table rules stay the same
+                if engine.rules() != rules {
+                    notification("The table rules have changed. You move to a new table with a fresh shoe and the count starts over", NotificationDuration::Long);
+                    engine = new_engine(engine.bankroll(), rules, seed);
+                    println!("Table rules: {rules}");
+                    println!("Seed: {}", engine.seed());
+                }
+
+                // If player can't cover the table minimum anymore, go back to main menu
+                if !engine.can_bet() {
+                    break;
+                }
+
+                save_progress(player, &engine)?;
+                notification("Saved", NotificationDuration::Short);
+
+                // The finished round stays on the table until the next bet, so its bet can be repeated
+                let last_bet = engine.round().map(|round| round.bet);
+                ask_bet(&engine, last_bet, input).map(|reply| reply.map(Move::Bet))
+            },
+            State::EarlySurrenderOffer => {
+                print_game_state(&engine, None, false);
+
+                ask_offer(&engine, "Dealer may have a blackjack. Do you want to surrender early?", input::InGameOptions::Surrender, "Surrender", "Play the hand", input)
+                    .map(|reply| reply.map(|surrender| Move::Act(if surrender { Action::Surrender } else { Action::Decline })))
+            },
+            State::InsuranceOffer(max_insurance) => {
+                print_game_state(&engine, None, false);
+
+                match ask_offer(&engine, "Dealer shows an ace. Do you want insurance?", input::InGameOptions::Insurance, "Insurance", "No insurance", input) {
+                    Some(Reply::Answer(true)) => ask_insurance_amount(max_insurance, input).map(|amount| Reply::Answer(Move::Act(Action::Insurance(amount)))),
+                    Some(Reply::Answer(false)) => Some(Reply::Answer(Move::Act(Action::Decline))),
+                    Some(Reply::Pause) => Some(Reply::Pause),
+                    None => None
+                }
+            },
+            State::EvenMoneyOffer => {
+                print_game_state(&engine, None, false);
+
+                ask_offer(&engine, "Dealer shows an ace. Do you want even money?", input::InGameOptions::Insurance, "Even money", "No thanks", input)
+                    .map(|reply| reply.map(|even_money| Move::Act(if even_money { Action::EvenMoney } else { Action::Decline })))
+            },
+            State::PlayerTurn(active) => {
+                print_game_state(&engine, Some(active), false);
+
+                ask_action(&engine, input).map(|reply| reply.map(Move::Act))
+            },
+            State::DealerTurn => {
+                print_game_state(&engine, None, true);
+
+                Some(Reply::Answer(Move::Step))
             }
-        }
+        };
 
-        // If player gets more than 21
-        if cards_value(&player_hand) > 21 {
-            println!("\n--- YOU LOST ---");
-            println!("You busted");
+        // The game is left when the input ends. The table is saved with the player, so an unfinished round is kept
+        let next_move = match reply {
+            Some(Reply::Answer(next_move)) => next_move,
+            Some(Reply::Pause) => {
+                if pause_menu(&engine, &stats, input) {
+                    continue;
+                }
+                return save_progress(player, &engine);
+            },
+            None => return save_progress(player, &engine)
+        };
 
-            continue;
-        }
+        // Decisions are compared to basic strategy before the engine moves on. None if there's nothing to compare
+        let review = match next_move {
+            Move::Act(action) if engine.hint().is_some() => Some(engine.review(action)),
+            _ => None
+        };
 
-        // Dealer's turn
-        print!("\r\x1B[7A\x1B[K--- DEALER'S TURN | BET: ${bet} ---\x1B[7B");
-        io::stdout().flush().unwrap();
-
-        // Print current game state
-        print_game_state(&player_hand, &dealer_hand, true);
-        while cards_value(&dealer_hand) <= 21 {
-            sleep(Duration::from_secs(1));
-
-            // Dealer stands on 17 or greater
-            if cards_value(&dealer_hand) < 17 {
-                deal_cards(&mut dealer_hand, &mut deck, 1)?;
-                // Print game state
-                print_game_state(&player_hand, &dealer_hand, true);
-                sleep(Duration::from_secs(1));
-            } else {
-                sleep(Duration::from_secs(1));
-                break;
-            }
-        }
+        let result = match next_move {
+            Move::Bet(bet) => {
+                println!("You are betting ${bet}");
+                engine.bet(bet)
+            },
+            Move::Act(action) => engine.act(action),
+            Move::Step => engine.step()
+        };
 
-        // If dealer gets more than 21
-        if cards_value(&dealer_hand) > 21 {
-            player.wealth = player.wealth.saturating_add(bet * 2);
+        match result {
+            Ok(events) => {
+                if let Some(mistake) = review {
+                    if let Some(mistake) = &mistake {
+                        let message = format!("Basic strategy plays {} here. {} costs about ${}", action_name(mistake.correct), action_name(mistake.played), mistake.cost_money());
+                        notification(&message, NotificationDuration::Long);
+                    }
+                    record_decision(player, mistake);
+                }
 
-            println!("\n--- YOU WON ---");
-            println!("Dealer busted. You won ${}", bet * 2);
+                let results = show_events(&engine, &events, &mut insurance_result);
+                stats.record(&events);
 
-            continue;
-        }
+                // A lost hand history line isn't worth stopping the game for
+                if events.contains(&Event::RoundFinished) && history::record_round(&player.id, &engine, &results).is_err() {
+                    notification("Couldn't write the hand history", NotificationDuration::Short);
+                }
+            },
+            Err(err) if err.is_fatal() => return Err(err.to_string()),
+            Err(err) => notification(&err.to_string(), NotificationDuration::Short)
+        };
 
-        // If player and dealer have same value
-        if cards_value(&player_hand) == cards_value(&dealer_hand) {
-            player.wealth = player.wealth.saturating_add(bet);
+        // Every action is saved right away, so quitting in the middle of a round can't undo it
+        save_progress(player, &engine)?;
+    }
 
-            println!("\n--- DRAW ---");
-            println!("You and dealer got hands of same value. You get ${bet} back");
+    end_session(player);
+    if engine.bankroll().is_zero() {
+        println!("You ran out of money. Returning to main menu...");
+    } else {
+        println!("You can't cover the table minimum of ${}. Returning to main menu...", engine.rules().limits.min);
+    }
+    pause(Duration::from_secs(2));
 
-            continue;
-        }
+    Ok(())
+}
 
-        // If player has more value than dealer
-        if cards_value(&player_hand) > cards_value(&dealer_hand) {
-            player.wealth = player.wealth.saturating_add(bet * 2);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::ScriptedInput;
+    use crate::save::{history_path, new_profile_id, use_test_dir};
+    use crate::utils::{set_pacing, Pacing};
 
-            println!("\n--- YOU WON ---");
-            println!("You were closer to 21. You won ${}", bet * 2);
+    /// Plays a new game with the script and returns the profile it created
+    fn play_new_game(name: &str, script: &[&str]) -> Player {
+        use_test_dir();
+        set_pacing(Pacing::Instant);
+        let id = new_profile_id(name);
 
-            continue;
-        }
+        let mut commands = vec![name];
+        commands.extend_from_slice(script);
+        new_game(&RuleSet::default(), Some(3), &mut ScriptedInput::new(commands));
 
-        // If dealer has more value than player
-        if cards_value(&player_hand) < cards_value(&dealer_hand) {
-            println!("\n--- YOU LOST ---");
-            println!("Dealer was closer to 21.");
+        load(&id).unwrap()
+    }
 
-            continue;
-        }
+    #[test]
+    fn scripted_session_is_saved_and_reviewed() {
+        let player = play_new_game("Scripted", &["10", "stand", "10", "stand"]);
+
+        let table = player.table.unwrap();
+        assert_eq!(table.seed, 3);
+        assert!(!table.round_in_progress());
+        assert_eq!(player.last_session.decisions, 2);
+        let history = std::fs::read_to_string(history_path(&player.id)).unwrap();
+        assert_eq!(history.lines().count(), 2);
     }
 
-    // If player runs out of money or somehow gets to u32 max, go back to main menu
-    if player.wealth == 0 {
-        println!("You ran out of money. Returning to main menu...");
-        sleep(Duration::from_secs(2));
+    #[test]
+    fn obo_dealer_blackjack_mentions_only_money_that_comes_back() {
+        let obo = RuleSet { original_bets_only: true, hole_card: HoleCardRule::NoHoleCard, ..RuleSet::default() };
+
+        let (_, text) = describe_round_outcome(RoundOutcome::DealerBlackjack, Money::from_cents(1000), &obo);
+        assert_eq!(text, "Dealer got a blackjack. Only the original bet is lost, you get $10 back");
+        let (_, text) = describe_round_outcome(RoundOutcome::DealerBlackjack, Money::ZERO, &obo);
+        assert_eq!(text, "Dealer got a blackjack");
     }
 
-    if player.wealth == u16::MAX {
-        println!("You have too much money. The casino can't provide for further wins. Returning to main menu...");
-        sleep(Duration::from_secs(2));
+    #[test]
+    fn session_review_is_kept_when_continuing_from_the_main_menu() {
+        let player = play_new_game("Continued", &["10", "stand", "pause", "4"]);
+        assert_eq!(player.last_session.decisions, 1);
+
+        load_game(&player.id, &RuleSet::default(), None, &mut ScriptedInput::new(["10", "stand"]));
+        let mut player = load(&player.id).unwrap();
+        assert_eq!(player.last_session.decisions, 2);
+
+        // The first decision after the session ended starts a new review
+        end_session(&player);
+        record_decision(&mut player, None);
+        assert_eq!(player.last_session.decisions, 1);
     }
 
-    Ok(())
+    #[test]
+    fn unfinished_round_survives_leaving_from_the_pause_menu_twice() {
+        let player = play_new_game("Paused", &["10", "pause", "4"]);
+        assert!(player.table.as_ref().is_some_and(|table| table.round_in_progress()));
+
+        load_game(&player.id, &RuleSet::default(), None, &mut ScriptedInput::new(["pause", "4"]));
+        let player = load(&player.id).unwrap();
+        assert!(player.table.is_some_and(|table| table.round_in_progress()));
+        assert_eq!(player.wealth, Money::ZERO);
+    }
 }
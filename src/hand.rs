@@ -0,0 +1,55 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::{evaluate, Card, HandValue, Rank};
+use crate::money::Money;
+
+/// A player's hand with its own bet. Splitting a pair creates more of these
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hand {
+    pub cards: Vec<Card>,
+    pub bet: Money,
+    pub split: bool,
+    pub doubled: bool
+}
+
+impl Hand {
+    /// Creates an empty hand with the passed bet
+    pub fn new(bet: Money) -> Hand {
+        Hand {
+            cards: Vec::new(),
+            bet,
+            split: false,
+            doubled: false
+        }
+    }
+
+    /// Evaluates the cards of the hand
+    pub fn evaluate(&self) -> HandValue {
+        evaluate(&self.cards)
+    }
+
+    /// Calculates the best total value of the hand
+    pub fn value(&self) -> u8 {
+        self.evaluate().total()
+    }
+
+    /// Returns true if the hand is over 21
+    pub fn is_bust(&self) -> bool {
+        self.evaluate().is_bust()
+    }
+
+    /// Returns true if the hand is a two card 21 that wasn't made by splitting
+    pub fn is_natural(&self) -> bool {
+        !self.split && self.evaluate().is_natural()
+    }
+
+    /// Returns true if the hand consists of two cards of the same value, e.g. 8-8 or K-10
+    pub fn is_pair(&self) -> bool {
+        self.cards.len() == 2 && self.cards[0].rank.value() == self.cards[1].rank.value()
+    }
+
+    /// Returns true if the hand was made by splitting aces
+    pub fn is_split_aces(&self) -> bool {
+        self.split && self.cards.first().is_some_and(|card| card.rank == Rank::Ace)
+    }
+}
@@ -0,0 +1,33 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+use blackjack::card::format_cards;
+use blackjack::engine::Engine;
+
+use crate::save::{history_path, save_dir, unix_time};
+
+/// Appends the finished round to the profile's hand history. Every line has the seed of the table and the number
+/// of the shoe, so the round can be dealt again by starting a game with the same seed
+///
+/// # Returns
+///
+/// Ok or Err if the history file couldn't be written
+pub fn record_round(id: &str, engine: &Engine, results: &[String]) -> io::Result<()> {
+    let Some(round) = engine.round() else {
+        return Ok(());
+    };
+
+    let hands = round.hands
+        .iter()
+        .map(|hand| format!("[{}] (${})", format_cards(&hand.cards), hand.bet))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let line = format!(
+        "{} | seed {} | shoe {} | dealer [{}] | hands {hands} | {}\n",
+        unix_time(), engine.seed(), engine.table().shuffles, format_cards(&round.dealer_hand), results.join(" / ")
+    );
+
+    fs::create_dir_all(save_dir())?;
+    let mut file = OpenOptions::new().create(true).append(true).open(history_path(id))?;
+    file.write_all(line.as_bytes())
+}
@@ -1,9 +1,22 @@
+use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use blackjack::engine::Action;
+use blackjack::money::Money;
 
 /// Enum for all main menu choices
 pub enum MainMenuOptions {
     NewGame,
     Continue,
+    Profiles,
+    TableRules,
+    Settings,
+    Drill,
+    Review,
     Exit
 }
 
@@ -12,24 +25,84 @@ impl TryFrom<u8> for MainMenuOptions {
     /// 
     /// # Returns
     /// 
-    /// MenuOptions if passed number is within 1-3, otherwise None
+    /// MenuOptions if passed number is within 1-8, otherwise None
     fn try_from(num: u8) -> Result<Self, Self::Error> {
         match num {
             1 => Ok(MainMenuOptions::NewGame),
             2 => Ok(MainMenuOptions::Continue),
-            3 => Ok(MainMenuOptions::Exit),
-            _ => Err(String::from(format!("No option for number {}", num)))
+            3 => Ok(MainMenuOptions::Profiles),
+            4 => Ok(MainMenuOptions::TableRules),
+            5 => Ok(MainMenuOptions::Settings),
+            6 => Ok(MainMenuOptions::Drill),
+            7 => Ok(MainMenuOptions::Review),
+            8 => Ok(MainMenuOptions::Exit),
+            _ => Err(format!("No option for number {num}"))
+        }
+    } 
+
+    type Error = String;
+}
+
+impl FromStr for MainMenuOptions {
+    type Err = String;
+
+    /// Reads an option from its number or name, e.g. "1" or "new"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(num) = s.parse::<u8>() {
+            return MainMenuOptions::try_from(num);
+        }
+
+        match s.to_lowercase().as_str() {
+            "n" | "new" | "new game" => Ok(MainMenuOptions::NewGame),
+            "c" | "continue" => Ok(MainMenuOptions::Continue),
+            "p" | "profiles" => Ok(MainMenuOptions::Profiles),
+            "r" | "rules" | "table rules" => Ok(MainMenuOptions::TableRules),
+            "s" | "settings" => Ok(MainMenuOptions::Settings),
+            "d" | "drill" | "practice" => Ok(MainMenuOptions::Drill),
+            "v" | "review" => Ok(MainMenuOptions::Review),
+            "q" | "quit" | "exit" => Ok(MainMenuOptions::Exit),
+            _ => Err(unknown_command(s, &["new", "continue", "profiles", "rules", "settings", "drill", "review", "exit"]))
+        }
+    }
+}
+
+/// Enum for all profile menu choices
+pub enum ProfileMenuOptions {
+    Create,
+    Rename,
+    Delete,
+    Back
+}
+
+impl TryFrom<u8> for ProfileMenuOptions {
+    /// Compares passed number and returns respective ProfileMenuOptions value
+    /// 
+    /// # Returns
+    /// 
+    /// ProfileMenuOptions if passed number is within 1-4, otherwise None
+    fn try_from(num: u8) -> Result<Self, Self::Error> {
+        match num {
+            1 => Ok(ProfileMenuOptions::Create),
+            2 => Ok(ProfileMenuOptions::Rename),
+            3 => Ok(ProfileMenuOptions::Delete),
+            4 => Ok(ProfileMenuOptions::Back),
+            _ => Err(format!("No option for number {num}"))
         }
     } 
 
     type Error = String;
 }
 
-/// Enum for all in-game choices
+/// Enum for all in-game choices. The value of each choice is the number the player types
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum InGameOptions {
-    Hit,
-    Stand,
-    DoubleDown
+    Hit = 1,
+    Stand = 2,
+    DoubleDown = 3,
+    Split = 4,
+    Surrender = 5,
+    Insurance = 6,
+    Decline = 7
 }
 
 impl TryFrom<u8> for InGameOptions {
@@ -37,15 +110,358 @@ impl TryFrom<u8> for InGameOptions {
     /// 
     /// # Returns
     /// 
-    /// MenuOptions if passed number is within 1-3, otherwise None
+    /// MenuOptions if passed number is within 1-7, otherwise None
     fn try_from(num: u8) -> Result<Self, Self::Error> {
         match num {
             1 => Ok(InGameOptions::Hit),
             2 => Ok(InGameOptions::Stand),
             3 => Ok(InGameOptions::DoubleDown),
-            _ => Err(String::from(format!("No option for number {}", num)))
+            4 => Ok(InGameOptions::Split),
+            5 => Ok(InGameOptions::Surrender),
+            6 => Ok(InGameOptions::Insurance),
+            7 => Ok(InGameOptions::Decline),
+            _ => Err(format!("No option for number {num}"))
         }
     } 
 
     type Error = String;
-}
\ No newline at end of file
+}
+
+impl InGameOptions {
+    /// Returns the letter that can be typed instead of the number
+    pub fn shortcut(&self) -> char {
+        match self {
+            InGameOptions::Hit => 'h',
+            InGameOptions::Stand => 's',
+            InGameOptions::DoubleDown => 'd',
+            InGameOptions::Split => 'p',
+            InGameOptions::Surrender => 'r',
+            InGameOptions::Insurance => 'i',
+            InGameOptions::Decline => 'n'
+        }
+    }
+
+    /// Returns the play for the hand the option stands for, or None for the answers to offers
+    pub fn play(&self) -> Option<Action> {
+        match self {
+            InGameOptions::Hit => Some(Action::Hit),
+            InGameOptions::Stand => Some(Action::Stand),
+            InGameOptions::DoubleDown => Some(Action::DoubleDown),
+            InGameOptions::Split => Some(Action::Split),
+            InGameOptions::Surrender => Some(Action::Surrender),
+            InGameOptions::Insurance | InGameOptions::Decline => None
+        }
+    }
+}
+
+impl FromStr for InGameOptions {
+    type Err = String;
+
+    /// Reads an option from its number, name or shortcut, e.g. "1", "hit" or "h"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(num) = s.parse::<u8>() {
+            return InGameOptions::try_from(num);
+        }
+
+        match s.to_lowercase().as_str() {
+            "h" | "hit" => Ok(InGameOptions::Hit),
+            "s" | "stand" => Ok(InGameOptions::Stand),
+            "d" | "double" | "double down" => Ok(InGameOptions::DoubleDown),
+            "p" | "split" => Ok(InGameOptions::Split),
+            "r" | "surrender" => Ok(InGameOptions::Surrender),
+            "i" | "insurance" | "even money" | "y" | "yes" => Ok(InGameOptions::Insurance),
+            "n" | "no" | "decline" => Ok(InGameOptions::Decline),
+            _ => Err(unknown_command(s, &["hit", "stand", "double", "split", "surrender", "insurance", "decline", "hint", "count", "help", "pause"]))
+        }
+    }
+}
+
+/// Enum for all pause menu choices
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PauseMenuOptions {
+    Resume = 1,
+    Stats = 2,
+    Settings = 3,
+    SaveAndQuit = 4
+}
+
+impl TryFrom<u8> for PauseMenuOptions {
+    /// Converts u8 to PauseMenuOptions
+    fn try_from(num: u8) -> Result<Self, Self::Error> {
+        match num {
+            1 => Ok(PauseMenuOptions::Resume),
+            2 => Ok(PauseMenuOptions::Stats),
+            3 => Ok(PauseMenuOptions::Settings),
+            4 => Ok(PauseMenuOptions::SaveAndQuit),
+            _ => Err(format!("No option for number {num}"))
+        }
+    }
+
+    type Error = String;
+}
+
+impl FromStr for PauseMenuOptions {
+    type Err = String;
+
+    /// Reads an option from its number or name, e.g. "1" or "resume"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(num) = s.parse::<u8>() {
+            return PauseMenuOptions::try_from(num);
+        }
+
+        match s.to_lowercase().as_str() {
+            "r" | "resume" | "back" => Ok(PauseMenuOptions::Resume),
+            "stats" => Ok(PauseMenuOptions::Stats),
+            "s" | "settings" => Ok(PauseMenuOptions::Settings),
+            "q" | "quit" | "save" => Ok(PauseMenuOptions::SaveAndQuit),
+            _ => Err(unknown_command(s, &["resume", "stats", "settings", "quit"]))
+        }
+    }
+}
+
+/// Enum for everything that can be typed at the prompts during a hand
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GameCommand {
+    Play(InGameOptions),
+    Help,
+    /// Show the basic strategy play
+    Hint,
+    /// Show or hide the count
+    Count,
+    /// Open the pause menu
+    Pause
+}
+
+impl FromStr for GameCommand {
+    type Err = String;
+
+    /// Reads a command, e.g. "h", "2", "?" or "quit"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "?" | "help" => Ok(GameCommand::Help),
+            "hint" | "tip" => Ok(GameCommand::Hint),
+            "count" => Ok(GameCommand::Count),
+            "q" | "quit" | "pause" | "menu" => Ok(GameCommand::Pause),
+            _ => s.parse().map(GameCommand::Play)
+        }
+    }
+}
+
+/// Enum for everything that can be typed at the bet prompt
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BetCommand {
+    Amount(Money),
+    /// Same bet as in the previous round
+    Rebet,
+    /// Twice the previous bet
+    Double,
+    /// Half of the previous bet
+    Half,
+    /// As much as possible
+    Max,
+    Help,
+    /// Show or hide the count
+    Count,
+    /// Open the pause menu
+    Pause
+}
+
+impl FromStr for BetCommand {
+    type Err = String;
+
+    /// Reads a bet command, e.g. "10", "$2.50", "rebet", "2x", "half" or "max"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "r" | "rebet" | "same" => Ok(BetCommand::Rebet),
+            "2x" | "x2" | "double" => Ok(BetCommand::Double),
+            "half" | "1/2" => Ok(BetCommand::Half),
+            "max" | "all" | "all in" => Ok(BetCommand::Max),
+            "?" | "help" => Ok(BetCommand::Help),
+            "count" => Ok(BetCommand::Count),
+            "q" | "quit" | "pause" | "menu" => Ok(BetCommand::Pause),
+            _ if s.starts_with(|c: char| c.is_ascii_digit() || c == '$' || c == '.') => s.parse().map(BetCommand::Amount),
+            _ => Err(unknown_command(s, &["rebet", "2x", "half", "max", "count", "help", "pause"]))
+        }
+    }
+}
+
+/// Creates the error message for an unknown command, suggesting the known command closest to it
+fn unknown_command(input: &str, known: &[&str]) -> String {
+    let input_lower = input.to_lowercase();
+    // Short inputs would be close to almost anything, so a suggestion needs fewer changes than the input has characters
+    let max_distance = input_lower.chars().count().saturating_sub(1).min(2);
+    let closest = known
+        .iter()
+        .map(|command| (edit_distance(&input_lower, command), command))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance);
+
+    match closest {
+        Some((_, command)) => format!("Unknown command '{input}'. Did you mean '{command}'?"),
+        None => format!("Unknown command '{input}'. Type ? for help")
+    }
+}
+
+/// Counts how many characters have to be inserted, removed or replaced to turn one text into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+
+    // Distances from the start of a to every start of b, one row at a time
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a_char != *b_char);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Source of the player's input. Menus and the game read every answer from one of these
+pub trait InputSource {
+    /// Reads the next line of input
+    ///
+    /// # Returns
+    ///
+    /// The line without surrounding whitespace or None once the input has ended
+    fn read_line(&mut self) -> Option<String>;
+}
+
+/// Input typed in a terminal. The typed line is cleared after reading to keep the screen tidy
+pub struct TerminalInput;
+
+impl InputSource for TerminalInput {
+    fn read_line(&mut self) -> Option<String> {
+        let line = read_stdin_line()?;
+
+        // Clear input to prevent bugs
+        print!("\x1B[A\r\x1B[K");
+        let _ = io::stdout().flush();
+
+        Some(line)
+    }
+}
+
+/// Input piped to stdin from a file or another program. It isn't echoed, so there is nothing to clear
+pub struct PipedInput;
+
+impl InputSource for PipedInput {
+    fn read_line(&mut self) -> Option<String> {
+        read_stdin_line()
+    }
+}
+
+/// Commands given in advance, e.g. from a script file or a test
+pub struct ScriptedInput {
+    commands: VecDeque<String>
+}
+
+impl ScriptedInput {
+    pub fn new<S: Into<String>>(commands: impl IntoIterator<Item = S>) -> ScriptedInput {
+        ScriptedInput {
+            commands: commands.into_iter().map(Into::into).collect()
+        }
+    }
+
+    /// Reads a script with one command per line. Lines starting with # are comments
+    ///
+    /// # Returns
+    ///
+    /// Ok containing the input or an Err if the file couldn't be read
+    pub fn from_file(path: &Path) -> io::Result<ScriptedInput> {
+        let script = fs::read_to_string(path)?;
+
+        Ok(ScriptedInput::new(script.lines().filter(|line| !line.trim_start().starts_with('#'))))
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn read_line(&mut self) -> Option<String> {
+        self.commands.pop_front().map(|command| String::from(command.trim()))
+    }
+}
+
+/// Returns terminal input if stdin is a terminal and piped input otherwise
+pub fn stdin_input() -> Box<dyn InputSource> {
+    if io::stdin().is_terminal() {
+        Box::new(TerminalInput)
+    } else {
+        Box::new(PipedInput)
+    }
+}
+
+/// Reads a line from stdin
+///
+/// # Returns
+///
+/// The line without surrounding whitespace or None if stdin is closed or can't be read
+fn read_stdin_line() -> Option<String> {
+    let mut input = String::new();
+
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(String::from(input.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_commands_are_read_from_numbers_names_and_shortcuts() {
+        assert!(matches!("2".parse(), Ok(GameCommand::Play(InGameOptions::Stand))));
+        assert!(matches!("H".parse(), Ok(GameCommand::Play(InGameOptions::Hit))));
+        assert!(matches!("double down".parse(), Ok(GameCommand::Play(InGameOptions::DoubleDown))));
+        assert!(matches!("yes".parse(), Ok(GameCommand::Play(InGameOptions::Insurance))));
+        assert!(matches!("?".parse(), Ok(GameCommand::Help)));
+        assert!(matches!("Hint".parse(), Ok(GameCommand::Hint)));
+        assert!(matches!("quit".parse(), Ok(GameCommand::Pause)));
+        assert!(matches!("9".parse::<GameCommand>(), Err(err) if err == "No option for number 9"));
+    }
+
+    #[test]
+    fn bet_commands_tell_amounts_from_words() {
+        assert!(matches!("$2.50".parse(), Ok(BetCommand::Amount(amount)) if amount == Money::from_cents(250)));
+        assert!(matches!(".5".parse(), Ok(BetCommand::Amount(amount)) if amount == Money::from_cents(50)));
+        assert!(matches!("REBET".parse(), Ok(BetCommand::Rebet)));
+        assert!(matches!("2x".parse(), Ok(BetCommand::Double)));
+        assert!(matches!("1/2".parse(), Ok(BetCommand::Half)));
+        assert!(matches!("all in".parse(), Ok(BetCommand::Max)));
+        assert!(matches!("menu".parse(), Ok(BetCommand::Pause)));
+        assert!(matches!("1.234".parse::<BetCommand>(), Err(err) if err == "'1.234' is not an amount of money"));
+    }
+
+    #[test]
+    fn unknown_commands_suggest_the_closest_one() {
+        assert!(matches!("stnad".parse::<GameCommand>(), Err(err) if err == "Unknown command 'stnad'. Did you mean 'stand'?"));
+        assert!(matches!("Spilt".parse::<GameCommand>(), Err(err) if err == "Unknown command 'Spilt'. Did you mean 'split'?"));
+        assert!(matches!("rebt".parse::<BetCommand>(), Err(err) if err == "Unknown command 'rebt'. Did you mean 'rebet'?"));
+        assert!(matches!("contineu".parse::<MainMenuOptions>(), Err(err) if err == "Unknown command 'contineu'. Did you mean 'continue'?"));
+
+        // Short or far off inputs get no suggestion
+        assert!(matches!("x".parse::<GameCommand>(), Err(err) if err == "Unknown command 'x'. Type ? for help"));
+        assert!(matches!("banana".parse::<BetCommand>(), Err(err) if err == "Unknown command 'banana'. Type ? for help"));
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_changes() {
+        assert_eq!(edit_distance("stand", "stand"), 0);
+        assert_eq!(edit_distance("stan", "stand"), 1);
+        assert_eq!(edit_distance("hitt", "hit"), 1);
+        assert_eq!(edit_distance("sit", "hit"), 1);
+        assert_eq!(edit_distance("", "max"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn scripted_input_trims_every_line_and_then_ends() {
+        let mut input = ScriptedInput::new(["  10 ", "stand"]);
+        assert_eq!(input.read_line().as_deref(), Some("10"));
+        assert_eq!(input.read_line().as_deref(), Some("stand"));
+        assert_eq!(input.read_line(), None);
+    }
+}
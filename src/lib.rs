@@ -0,0 +1,12 @@
+//! Blackjack rules, cards and a game engine without any terminal input or output.
+//! The console game is built on top of this library
+
+pub mod card;
+pub mod count;
+pub mod engine;
+pub mod hand;
+pub mod money;
+pub mod round;
+pub mod rules;
+pub mod shoe;
+pub mod strategy;
@@ -1,16 +1,108 @@
 use figlet_rs::FIGfont;
-use utils::notification;
+use blackjack::rules::RuleSet;
+use input::{InputSource, ScriptedInput};
+use settings::{settings_menu, Settings};
+use utils::{notification, pause, Pacing};
 
-use std::io::{self, Write};
-use std::thread::sleep;
+use std::env;
+use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
 
+mod drill;
 mod game;
+mod history;
+mod profiles;
+mod review;
+mod settings;
+mod systems;
 pub mod save;
 pub mod utils;
 pub mod input;
 
+/// Command line options of the game
+struct Args {
+    save_dir: Option<PathBuf>,
+    /// Seed for the shoes of new tables
+    seed: Option<u64>,
+    /// File of commands to play instead of reading stdin
+    script: Option<PathBuf>,
+    /// Pacing for this run instead of the saved setting
+    pacing: Option<Pacing>
+}
+
+/// Reads the command line options
+///
+/// # Returns
+///
+/// Ok containing the options or an Err describing the invalid option
+fn parse_args() -> Result<Args, String> {
+    let mut parsed = Args { save_dir: None, seed: None, script: None, pacing: None };
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--data-dir" => match args.next() {
+                Some(dir) => parsed.save_dir = Some(PathBuf::from(dir)),
+                None => return Err(String::from("--data-dir needs a directory"))
+            },
+            "--script" => match args.next() {
+                Some(file) => parsed.script = Some(PathBuf::from(file)),
+                None => return Err(String::from("--script needs a file"))
+            },
+            "--pacing" => match args.next() {
+                Some(pacing) => parsed.pacing = Some(pacing.parse()?),
+                None => return Err(String::from("--pacing needs normal, fast or instant"))
+            },
+            "--seed" => match args.next() {
+                Some(seed) => parsed.seed = Some(parse_seed(&seed)?),
+                None => return Err(String::from("--seed needs a number"))
+            },
+            _ => if let Some(dir) = arg.strip_prefix("--data-dir=") {
+                parsed.save_dir = Some(PathBuf::from(dir));
+            } else if let Some(seed) = arg.strip_prefix("--seed=") {
+                parsed.seed = Some(parse_seed(seed)?);
+            } else if let Some(file) = arg.strip_prefix("--script=") {
+                parsed.script = Some(PathBuf::from(file));
+            } else if let Some(pacing) = arg.strip_prefix("--pacing=") {
+                parsed.pacing = Some(pacing.parse()?);
+            } else {
+                return Err(format!("Unknown option {arg}"));
+            }
+        };
+    }
+
+    Ok(parsed)
+}
+
+/// Reads the value of the --seed option
+fn parse_seed(seed: &str) -> Result<u64, String> {
+    seed.parse().map_err(|_| format!("Seed must be a number between 0 and {}", u64::MAX))
+}
+
 fn main() -> io::Result<()> {
+    // Read command line options before anything is saved or loaded
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("Usage: blackjack [--data-dir <directory>] [--seed <number>] [--script <file>] [--pacing <normal|fast|instant>]");
+            eprintln!("The save directory can also be set with the {} environment variable", save::SAVE_DIR_ENV);
+            return Ok(());
+        }
+    };
+    if let Some(dir) = args.save_dir {
+        if let Err(err) = save::set_save_dir(dir) {
+            eprintln!("{err}");
+        }
+    }
+
+    // The pacing option only applies to this run, the saved setting stays the same
+    let settings = Settings::load();
+    utils::set_pacing(args.pacing.unwrap_or(settings.pacing));
+    settings::set_show_count(settings.show_count);
+    settings::set_counting_system(systems::find_system(&settings.counting_system).unwrap_or_default());
+
     // Create new big font for the intro
     let title = "Blackjack";
     let font = FIGfont::standard().unwrap();
@@ -22,53 +114,94 @@ fn main() -> io::Result<()> {
     } else {
         println!("{title}");
     }
-    sleep(Duration::from_secs(1));
+    pause(Duration::from_secs(1));
+
+    // Commands are read from the script if there is one, otherwise from stdin
+    let mut input: Box<dyn InputSource> = match &args.script {
+        Some(path) => match ScriptedInput::from_file(path) {
+            Ok(script) => Box::new(script),
+            Err(err) => {
+                eprintln!("Couldn't read the script {}: {err}", path.display());
+                return Ok(());
+            }
+        },
+        None => input::stdin_input()
+    };
 
-    menu();
+    menu(args.seed, input.as_mut());
 
     Ok(())
 }
 
-/// Main menu of the game
-fn menu() {
+/// Main menu of the game. New tables shuffle from the passed seed if there is one.
+/// The game exits when the input ends
+fn menu(seed: Option<u64>, input: &mut dyn InputSource) {
+    // Rules of the table the next game is played at
+    let mut rules = RuleSet::default();
+
+    // The single save file from before profiles becomes a profile of its own
+    match save::import_single_save() {
+        Ok(Some(name)) => notification(&format!("Imported the old save file as profile {name}"), utils::NotificationDuration::Long),
+        Ok(None) => {},
+        Err(err) => notification(&format!("Couldn't import the old save file: {err}"), utils::NotificationDuration::Long)
+    };
+
     'main_menu: loop {
         println!("---");
         println!("Main menu");
         println!("1. New game");
         println!("2. Continue");
-        println!("3. Exit");
+        println!("3. Profiles");
+        println!("4. Table rules");
+        println!("5. Settings");
+        println!("6. Basic strategy drill");
+        println!("7. Session review");
+        println!("8. Exit");
 
         loop {
             // Get user input
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).expect("Failed to read line");
+            let Some(line) = input.read_line() else {
+                break 'main_menu;
+            };
 
-            // Clear input to prevent bugs
-            print!("\x1B[A\r\x1B[K");
-            io::stdout().flush().unwrap();
-        
             // Get MenuOption from input if it's valid
-            let option = match input.trim().parse::<u8>() {
-                Ok(num) => match input::MainMenuOptions::try_from(num) {
-                    Ok(option) => option,
-                    Err(err) => {
-                        notification(&format!("{}", err), utils::NotificationDuration::Short);
-                        continue;
-                    }
-                },
-                Err(_) => {
-                    notification("Input must be a number", utils::NotificationDuration::Short);
+            let option = match line.parse::<input::MainMenuOptions>() {
+                Ok(option) => option,
+                Err(err) => {
+                    notification(&err, utils::NotificationDuration::Short);
                     continue;
                 }
             };
             
             match option {
                 input::MainMenuOptions::NewGame => {
-                    game::new_game();
+                    game::new_game(&rules, seed, input);
                     break;
                 },
                 input::MainMenuOptions::Continue => {
-                    game::load_game();
+                    if let Some(id) = profiles::pick_profile("Choose a profile to continue", input) {
+                        game::load_game(&id, &rules, seed, input);
+                    }
+                    break;
+                },
+                input::MainMenuOptions::Profiles => {
+                    profiles::profiles_menu(input);
+                    break;
+                },
+                input::MainMenuOptions::TableRules => {
+                    rules_menu(&mut rules, input);
+                    break;
+                },
+                input::MainMenuOptions::Settings => {
+                    settings_menu(input);
+                    break;
+                },
+                input::MainMenuOptions::Drill => {
+                    drill::drill(&rules, input);
+                    break;
+                },
+                input::MainMenuOptions::Review => {
+                    review::session_review(input);
                     break;
                 },
                 input::MainMenuOptions::Exit => break 'main_menu,
@@ -76,3 +209,41 @@ fn menu() {
         }
     }
 }
+
+/// Lets the player pick the table the next game is played at. The rules stay the same if the input ends
+fn rules_menu(rules: &mut RuleSet, input: &mut dyn InputSource) {
+    let presets = RuleSet::presets();
+
+    println!("---");
+    println!("Current rules: {rules}");
+    println!("Choose a table");
+    for (index, (name, preset)) in presets.iter().enumerate() {
+        println!("{}. {name} ({preset})", index + 1);
+    }
+
+    loop {
+        // Get user input
+        let Some(line) = input.read_line() else {
+            return;
+        };
+
+        // Get the chosen preset if the input is valid
+        let index = match line.parse::<usize>() {
+            Ok(num) if (1..=presets.len()).contains(&num) => num - 1,
+            Ok(num) => {
+                notification(&format!("No option for number {num}"), utils::NotificationDuration::Short);
+                continue;
+            },
+            Err(_) => {
+                notification("Input must be a number", utils::NotificationDuration::Short);
+                continue;
+            }
+        };
+
+        let (name, preset) = &presets[index];
+        *rules = preset.clone();
+        notification(&format!("Playing at {name}"), utils::NotificationDuration::Short);
+
+        break;
+    }
+}
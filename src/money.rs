@@ -0,0 +1,167 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+
+/// Error message for arithmetic that doesn't fit in Money
+pub const MONEY_OVERFLOW: &str = "Amount of money is too large";
+
+/// Exact amount of money stored as whole cents
+///
+/// All arithmetic is checked so that a payout can never silently wrap or clamp
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Money(u64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    /// Creates an amount from whole dollars
+    ///
+    /// # Returns
+    ///
+    /// Money or None if the amount doesn't fit
+    pub fn from_dollars(dollars: u64) -> Option<Money> {
+        dollars.checked_mul(100).map(Money)
+    }
+
+    /// Creates an amount from cents
+    pub const fn from_cents(cents: u64) -> Money {
+        Money(cents)
+    }
+
+    /// Returns the amount in cents
+    pub fn cents(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns true if the amount is zero
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.0.checked_sub(other.0).map(Money)
+    }
+
+    pub fn checked_mul(self, times: u64) -> Option<Money> {
+        self.0.checked_mul(times).map(Money)
+    }
+
+    /// Multiplies the amount by `numerator / denominator`, e.g. 3/2 for a blackjack payout.
+    /// Fractions of a cent are rounded down
+    pub fn checked_mul_ratio(self, numerator: u64, denominator: u64) -> Option<Money> {
+        self.0.checked_mul(numerator)?.checked_div(denominator).map(Money)
+    }
+
+    /// Returns half of the amount. Half a cent is rounded down
+    pub fn half(self) -> Money {
+        Money(self.0 / 2)
+    }
+
+    /// Sums amounts together
+    ///
+    /// # Returns
+    ///
+    /// Money or None if the sum doesn't fit
+    pub fn checked_sum<'a>(amounts: impl IntoIterator<Item = &'a Money>) -> Option<Money> {
+        amounts.into_iter().try_fold(Money::ZERO, |sum, amount| sum.checked_add(*amount))
+    }
+}
+
+impl fmt::Display for Money {
+    /// Formats whole dollars without decimals, e.g. "10", and other amounts with cents, e.g. "7.50"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dollars = self.0 / 100;
+        let cents = self.0 % 100;
+
+        if cents == 0 {
+            write!(f, "{dollars}")
+        } else {
+            write!(f, "{dollars}.{cents:02}")
+        }
+    }
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    /// Reads an amount like "10", "$10", "2.5" or "7.50". More than two decimals are not allowed
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix('$').unwrap_or(s);
+        let invalid = || format!("'{s}' is not an amount of money");
+
+        // Split the text to dollars and cents
+        let (dollars, cents) = match s.split_once('.') {
+            Some((dollars, cents)) => (dollars, cents),
+            None => (s, "")
+        };
+
+        if dollars.is_empty() && cents.is_empty() {
+            return Err(invalid());
+        }
+        if !dollars.chars().all(|c| c.is_ascii_digit()) || !cents.chars().all(|c| c.is_ascii_digit()) || cents.len() > 2 {
+            return Err(invalid());
+        }
+
+        let dollars: u64 = if dollars.is_empty() { 0 } else { dollars.parse().map_err(|_| invalid())? };
+        // "2.5" means 50 cents, not 5
+        let cents: u64 = match cents.len() {
+            0 => 0,
+            1 => cents.parse::<u64>().map_err(|_| invalid())? * 10,
+            _ => cents.parse().map_err(|_| invalid())?
+        };
+
+        Money::from_dollars(dollars)
+            .and_then(|amount| amount.checked_add(Money(cents)))
+            .ok_or_else(|| String::from("Amount is too large"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the parsed amount in cents
+    fn parse(s: &str) -> Result<u64, String> {
+        s.parse::<Money>().map(|amount| amount.cents())
+    }
+
+    #[test]
+    fn parses_dollars_and_cents() {
+        assert_eq!(parse("10"), Ok(1000));
+        assert_eq!(parse("$10"), Ok(1000));
+        assert_eq!(parse(" 7.50 "), Ok(750));
+        assert_eq!(parse("2.5"), Ok(250));
+        assert_eq!(parse("2.05"), Ok(205));
+        assert_eq!(parse(".25"), Ok(25));
+        assert_eq!(parse("3."), Ok(300));
+        assert_eq!(parse("0"), Ok(0));
+    }
+
+    #[test]
+    fn rejects_text_that_isnt_an_amount() {
+        for text in ["", "$", ".", "1.234", "-5", "+5", "1,000", "1.2.3", "ten", "$ 5", "5$"] {
+            assert!(parse(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_amounts_that_dont_fit() {
+        assert_eq!(parse(&format!("{}", u64::MAX / 100 + 1)), Err(String::from("Amount is too large")));
+        assert!(parse(&format!("{}.99", u64::MAX / 100)).is_err());
+        assert!(parse("99999999999999999999999").is_err());
+    }
+
+    #[test]
+    fn formats_cents_only_when_there_are_some() {
+        assert_eq!(Money::from_cents(1000).to_string(), "10");
+        assert_eq!(Money::from_cents(750).to_string(), "7.50");
+        assert_eq!(Money::from_cents(5).to_string(), "0.05");
+        assert_eq!(parse(&Money::from_cents(123_456).to_string()), Ok(123_456));
+    }
+}
@@ -0,0 +1,195 @@
+use whoami::fallible::realname;
+
+use crate::input::{self, InputSource};
+use crate::save::{delete_profile, list_profiles, load, save, unix_time, LoadError, Player};
+use crate::utils::{notification, NotificationDuration};
+
+// Longest allowed player name
+const MAX_NAME_LEN: usize = 32;
+
+/// Formats the last played time relative to now, e.g. "2 hours ago"
+fn format_last_played(last_played: u64) -> String {
+    if last_played == 0 {
+        return String::from("unknown");
+    }
+
+    let seconds = unix_time().saturating_sub(last_played);
+    match seconds {
+        0..=59 => String::from("just now"),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400)
+    }
+}
+
+/// Prints every profile as a numbered list with its bankroll and last played time
+fn print_profiles(profiles: &[(String, Result<Player, LoadError>)]) {
+    for (index, (id, player)) in profiles.iter().enumerate() {
+        match player {
+            Ok(player) => {
+                let unfinished = if player.table.as_ref().is_some_and(|table| table.round_in_progress()) { " | Round unfinished" } else { "" };
+                println!("{}. {} | ${} | Last played: {}{unfinished}", index + 1, player.name, player.wealth, format_last_played(player.last_played));
+            },
+            Err(err) => println!("{}. {id} | {err}", index + 1)
+        }
+    }
+}
+
+/// Lets the player choose one of the saved profiles
+///
+/// # Returns
+///
+/// Id of the chosen profile or None if there are no profiles, the player went back or the input ended
+pub fn pick_profile(title: &str, input: &mut dyn InputSource) -> Option<String> {
+    let profiles = list_profiles();
+    if profiles.is_empty() {
+        notification("There are no saved profiles. Start a new game first", NotificationDuration::Long);
+
+        return None;
+    }
+
+    println!("---");
+    println!("{title}");
+    print_profiles(&profiles);
+    println!("0. Back");
+
+    loop {
+        // Get the chosen profile if the input is valid
+        match input.read_line()?.parse::<usize>() {
+            Ok(0) => return None,
+            Ok(num) if num <= profiles.len() => return Some(profiles[num - 1].0.clone()),
+            Ok(num) => {
+                notification(&format!("No option for number {num}"), NotificationDuration::Short);
+            },
+            Err(_) => {
+                notification("Input must be a number", NotificationDuration::Short);
+            }
+        };
+    }
+}
+
+/// Asks the player for a name. The user name of the PC is suggested
+///
+/// # Returns
+///
+/// Name between 1 and 32 characters or None if the input ended
+pub fn ask_player_name(input: &mut dyn InputSource) -> Option<String> {
+    // Get user name from user's PC
+    let default_name = match realname() {
+        Ok(name) => name,
+        Err(_) => String::from("User")
+    };
+
+    println!("Enter your name or leave it empty to play as {default_name}");
+
+    loop {
+        let name = input.read_line()?;
+
+        if name.is_empty() {
+            return Some(default_name);
+        }
+        if name.chars().count() > MAX_NAME_LEN {
+            notification(&format!("Name can be at most {MAX_NAME_LEN} characters long"), NotificationDuration::Short);
+
+            continue;
+        }
+
+        return Some(name);
+    }
+}
+
+/// Menu for creating, renaming and deleting profiles
+pub fn profiles_menu(input: &mut dyn InputSource) {
+    loop {
+        println!("---");
+        println!("Profiles");
+        print_profiles(&list_profiles());
+        println!("---");
+        println!("1. Create profile");
+        println!("2. Rename profile");
+        println!("3. Delete profile");
+        println!("4. Back");
+
+        // Get ProfileMenuOption from input if it's valid
+        let Some(line) = input.read_line() else {
+            return;
+        };
+        let option = match line.parse::<u8>() {
+            Ok(num) => match input::ProfileMenuOptions::try_from(num) {
+                Ok(option) => option,
+                Err(err) => {
+                    notification(&err, NotificationDuration::Short);
+                    continue;
+                }
+            },
+            Err(_) => {
+                notification("Input must be a number", NotificationDuration::Short);
+                continue;
+            }
+        };
+
+        match option {
+            input::ProfileMenuOptions::Create => create_profile(input),
+            input::ProfileMenuOptions::Rename => rename_profile(input),
+            input::ProfileMenuOptions::Delete => remove_profile(input),
+            input::ProfileMenuOptions::Back => break
+        };
+    }
+}
+
+/// Creates a new profile without starting a game
+fn create_profile(input: &mut dyn InputSource) {
+    let Some(name) = ask_player_name(input) else {
+        return;
+    };
+    let mut player = Player::new(name);
+
+    match save(&mut player) {
+        Ok(_) => notification(&format!("Created profile {}", player.name), NotificationDuration::Short),
+        Err(_) => notification("An error occurred when saving", NotificationDuration::Long)
+    };
+}
+
+/// Changes the name of a profile. The save file stays the same
+fn rename_profile(input: &mut dyn InputSource) {
+    let Some(id) = pick_profile("Which profile do you want to rename?", input) else {
+        return;
+    };
+
+    let mut player = match load(&id) {
+        Ok(player) => player,
+        Err(err) => {
+            notification(&err.to_string(), NotificationDuration::Long);
+            return;
+        }
+    };
+
+    let old_name = player.name.clone();
+    let Some(name) = ask_player_name(input) else {
+        return;
+    };
+    player.name = name;
+
+    match save(&mut player) {
+        Ok(_) => notification(&format!("Renamed {old_name} to {}", player.name), NotificationDuration::Short),
+        Err(_) => notification("An error occurred when saving", NotificationDuration::Long)
+    };
+}
+
+/// Deletes a profile after the player confirms it
+fn remove_profile(input: &mut dyn InputSource) {
+    let Some(id) = pick_profile("Which profile do you want to delete?", input) else {
+        return;
+    };
+
+    println!("Type \"delete\" to delete the profile for good. Anything else cancels");
+    if input.read_line().as_deref() != Some("delete") {
+        notification("Nothing was deleted", NotificationDuration::Short);
+        return;
+    }
+
+    match delete_profile(&id) {
+        Ok(_) => notification("Profile deleted", NotificationDuration::Short),
+        Err(err) => notification(&format!("Couldn't delete the profile: {err}"), NotificationDuration::Long)
+    };
+}
@@ -0,0 +1,115 @@
+use std::sync::Mutex;
+
+use serde::{Serialize, Deserialize};
+
+use blackjack::card::format_cards;
+use blackjack::money::Money;
+use blackjack::strategy::Mistake;
+
+use crate::game::action_name;
+use crate::input::InputSource;
+use crate::profiles::pick_profile;
+use crate::save::{load, Player};
+use crate::utils::{notification, NotificationDuration};
+
+/// Decisions made in one game session, compared to basic strategy
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SessionReview {
+    /// Every decision that basic strategy has an answer to
+    pub decisions: u32,
+    /// Decisions that differ from basic strategy, in the order they were made
+    pub mistakes: Vec<Mistake>
+}
+
+impl SessionReview {
+    /// Counts a decision and keeps it if it was a mistake
+    pub fn record(&mut self, mistake: Option<Mistake>) {
+        self.decisions += 1;
+        if let Some(mistake) = mistake {
+            self.mistakes.push(mistake);
+        }
+    }
+
+    /// Returns the expected loss of every mistake together
+    pub fn total_cost(&self) -> Money {
+        Money::from_cents(self.mistakes.iter().map(|mistake| mistake.cost_money().cents()).sum())
+    }
+}
+
+// Profiles whose game session is going on in this run of the program. A session lasts until the player can't bet
+// anymore or the program exits, so leaving to the main menu and continuing keeps the same session
+static SESSIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Starts a new session for the player with an empty review
+pub fn start_session(player: &mut Player) {
+    let mut sessions = SESSIONS.lock().unwrap_or_else(|err| err.into_inner());
+    if !sessions.contains(&player.id) {
+        sessions.push(player.id.clone());
+    }
+
+    player.last_session = SessionReview::default();
+}
+
+/// Records a decision in the review of the player's current session. The first decision of a new session clears
+/// the review of the previous one
+pub fn record_decision(player: &mut Player, mistake: Option<Mistake>) {
+    let mut sessions = SESSIONS.lock().unwrap_or_else(|err| err.into_inner());
+    if !sessions.contains(&player.id) {
+        sessions.push(player.id.clone());
+        player.last_session = SessionReview::default();
+    }
+
+    player.last_session.record(mistake);
+}
+
+/// Ends the player's current session. Its review is kept until the next session makes a decision
+pub fn end_session(player: &Player) {
+    let mut sessions = SESSIONS.lock().unwrap_or_else(|err| err.into_inner());
+    sessions.retain(|id| *id != player.id);
+}
+
+/// Explains a mistake in one line, e.g. "[8♠, 8♥] against [10♦] (pair of 8s against 10): you played Stand,
+/// basic strategy plays Split. Costs about $0.24"
+pub fn describe_mistake(mistake: &Mistake) -> String {
+    format!(
+        "[{}] against [{}] ({}): you played {}, basic strategy plays {}. Costs about ${}",
+        format_cards(&mistake.cards),
+        mistake.upcard,
+        mistake.situation(),
+        action_name(mistake.played),
+        action_name(mistake.correct),
+        mistake.cost_money()
+    )
+}
+
+/// Prints every mistake of the last game session of a profile with its cost and the correct play
+pub fn session_review(input: &mut dyn InputSource) {
+    let Some(id) = pick_profile("Choose a profile to review", input) else {
+        return;
+    };
+    let player = match load(&id) {
+        Ok(player) => player,
+        Err(err) => {
+            notification(&err.to_string(), NotificationDuration::Long);
+            return;
+        }
+    };
+    let review = &player.last_session;
+
+    println!("---");
+    println!("Last session of {}", player.name);
+    if review.decisions == 0 {
+        println!("There are no decisions to review. Play a game first");
+        return;
+    }
+
+    for (index, mistake) in review.mistakes.iter().enumerate() {
+        println!("{}. {}", index + 1, describe_mistake(mistake));
+    }
+
+    let right = review.decisions - review.mistakes.len() as u32;
+    println!("{right} of {} decisions followed basic strategy", review.decisions);
+    if !review.mistakes.is_empty() {
+        println!("Mistakes cost about ${} in total", review.total_cost());
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::{evaluate, Card};
+use crate::count::DealtCards;
+use crate::hand::Hand;
+use crate::money::Money;
+use crate::rules::RuleSet;
+use crate::shoe::Shoe;
+
+/// Enum for the phases of a round. A round continued from a save starts again from the beginning of its phase
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundPhase {
+    /// Early surrender may be offered before the dealer peeks
+    EarlySurrender,
+    /// Insurance or even money may be offered when the dealer shows an ace
+    Insurance,
+    /// Naturals are paid and the dealer peeks for a blackjack
+    Naturals,
+    /// Player plays the active hand
+    PlayerTurn,
+    /// Dealer plays and every hand is settled
+    DealerTurn,
+    /// Every bet is settled. The round stays on the table until the next bet
+    Finished
+}
+
+/// A round in progress with everything needed to continue it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Round {
+    /// Original bet of the round
+    pub bet: Money,
+    pub hands: Vec<Hand>,
+    /// Index of the hand being played
+    pub active: usize,
+    pub dealer_hand: Vec<Card>,
+    pub insurance: Money,
+    pub surrendered: bool,
+    pub phase: RoundPhase
+}
+
+impl Round {
+    /// Creates a round with the passed bet and no cards dealt yet
+    pub fn new(bet: Money) -> Round {
+        Round {
+            bet,
+            hands: vec![Hand::new(bet)],
+            active: 0,
+            dealer_hand: Vec::new(),
+            insurance: Money::ZERO,
+            surrendered: false,
+            phase: RoundPhase::EarlySurrender
+        }
+    }
+
+    /// Returns true if the dealer has a two card 21
+    pub fn dealer_blackjack(&self) -> bool {
+        evaluate(&self.dealer_hand).is_natural()
+    }
+
+    /// Returns the total amount of money bet on the player's hands
+    pub fn total_bet(&self) -> Option<Money> {
+        Money::checked_sum(self.hands.iter().map(|hand| &hand.bet))
+    }
+}
+
+/// The table the player sits at: its rules, the shoe in its current order and the round being played or the previous one
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Table {
+    pub rules: RuleSet,
+    pub shoe: Shoe,
+    pub round: Option<Round>,
+    /// Seed every shuffle of the table is derived from
+    pub seed: u64,
+    /// Amount of times the shoe has been shuffled
+    pub shuffles: u32,
+    /// Cards dealt from the shoe since the last shuffle, including a hole card that hasn't been turned over
+    pub dealt: DealtCards
+}
+
+impl Table {
+    /// Creates a table with a new shoe. The shoe gets shuffled before the first round
+    pub fn new(rules: RuleSet, seed: u64) -> Table {
+        Table {
+            shoe: Shoe::new(rules.decks, rules.penetration),
+            rules,
+            round: None,
+            seed,
+            shuffles: 0,
+            dealt: DealtCards::default()
+        }
+    }
+
+    /// Returns true if a round has been dealt and not settled yet
+    pub fn round_in_progress(&self) -> bool {
+        self.round.as_ref().is_some_and(|round| round.phase != RoundPhase::Finished)
+    }
+}
@@ -0,0 +1,300 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+use crate::money::Money;
+
+/// Enum for how much a natural blackjack pays
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlackjackPayout {
+    ThreeToTwo,
+    SixToFive,
+    EvenMoney
+}
+
+impl BlackjackPayout {
+    /// Calculates the winnings of a natural for the passed bet. The bet itself is not included
+    ///
+    /// # Returns
+    ///
+    /// Money or None if the winnings don't fit
+    pub fn winnings(&self, bet: Money) -> Option<Money> {
+        match self {
+            BlackjackPayout::ThreeToTwo => bet.checked_mul_ratio(3, 2),
+            BlackjackPayout::SixToFive => bet.checked_mul_ratio(6, 5),
+            BlackjackPayout::EvenMoney => Some(bet)
+        }
+    }
+
+    /// Returns the winnings of a natural per unit of the bet, e.g. 1.5 for 3:2
+    pub fn ratio(&self) -> f64 {
+        match self {
+            BlackjackPayout::ThreeToTwo => 1.5,
+            BlackjackPayout::SixToFive => 1.2,
+            BlackjackPayout::EvenMoney => 1.0
+        }
+    }
+}
+
+impl fmt::Display for BlackjackPayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlackjackPayout::ThreeToTwo => write!(f, "3:2"),
+            BlackjackPayout::SixToFive => write!(f, "6:5"),
+            BlackjackPayout::EvenMoney => write!(f, "1:1")
+        }
+    }
+}
+
+/// Enum for which starting hands may be doubled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoubleRule {
+    AnyTwo,
+    NineToEleven,
+    TenToEleven
+}
+
+impl DoubleRule {
+    /// Returns true if a two card hand with the passed total may be doubled
+    pub fn allows(&self, hand_total: u8) -> bool {
+        match self {
+            DoubleRule::AnyTwo => true,
+            DoubleRule::NineToEleven => (9..=11).contains(&hand_total),
+            DoubleRule::TenToEleven => (10..=11).contains(&hand_total)
+        }
+    }
+}
+
+impl fmt::Display for DoubleRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DoubleRule::AnyTwo => write!(f, "Double any two"),
+            DoubleRule::NineToEleven => write!(f, "Double 9-11"),
+            DoubleRule::TenToEleven => write!(f, "Double 10-11")
+        }
+    }
+}
+
+/// Enum for when the player may surrender
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SurrenderRule {
+    NoSurrender,
+    Late,
+    Early
+}
+
+impl fmt::Display for SurrenderRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurrenderRule::NoSurrender => write!(f, "No surrender"),
+            SurrenderRule::Late => write!(f, "Late surrender"),
+            SurrenderRule::Early => write!(f, "Early surrender")
+        }
+    }
+}
+
+/// Enum for how the dealer's second card is dealt and checked for blackjack
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HoleCardRule {
+    /// Dealer gets a hole card and checks it for blackjack before the player acts
+    Peek,
+    /// Dealer gets a hole card but a blackjack is revealed only after the player acts
+    NoPeek,
+    /// European style. Dealer gets the second card only after the player acts
+    NoHoleCard
+}
+
+impl fmt::Display for HoleCardRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HoleCardRule::Peek => write!(f, "Dealer peeks"),
+            HoleCardRule::NoPeek => write!(f, "No peek"),
+            HoleCardRule::NoHoleCard => write!(f, "No hole card")
+        }
+    }
+}
+
+/// Smallest and largest bet of the table and the steps bets go up in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BetLimits {
+    pub min: Money,
+    pub max: Money,
+    /// Every bet has to be a multiple of this
+    pub increment: Money
+}
+
+impl BetLimits {
+    /// Creates limits from whole dollars
+    pub fn from_dollars(min: u64, max: u64, increment: u64) -> BetLimits {
+        BetLimits {
+            min: Money::from_cents(min.saturating_mul(100)),
+            max: Money::from_cents(max.saturating_mul(100)),
+            increment: Money::from_cents(increment.saturating_mul(100))
+        }
+    }
+
+    /// Rounds the amount down to the nearest allowed step. An increment of 0 allows every amount
+    pub fn round_down(&self, amount: Money) -> Money {
+        match self.increment.cents() {
+            0 => amount,
+            increment => Money::from_cents(amount.cents() - amount.cents() % increment)
+        }
+    }
+
+    /// Returns the largest bet allowed with the passed bankroll
+    pub fn max_bet(&self, bankroll: Money) -> Money {
+        self.round_down(self.max.min(bankroll))
+    }
+}
+
+impl Default for BetLimits {
+    /// Bets from $1 to $500 in whole dollars
+    fn default() -> Self {
+        BetLimits::from_dollars(1, 500, 1)
+    }
+}
+
+impl fmt::Display for BetLimits {
+    /// Formats the limits, e.g. "Bets $5-$1000 in steps of $5". The steps are left out if any amount of cents is allowed
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bets ${}-${}", self.min, self.max)?;
+        if self.increment.cents() > 1 {
+            write!(f, " in steps of ${}", self.increment)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// House rules of the table
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub dealer_hits_soft_17: bool,
+    pub blackjack_payout: BlackjackPayout,
+    pub double_rule: DoubleRule,
+    pub double_after_split: bool,
+    pub max_split_hands: u8,
+    pub hit_split_aces: bool,
+    pub decks: u8,
+    pub penetration: f32,
+    pub surrender: SurrenderRule,
+    pub hole_card: HoleCardRule,
+    pub original_bets_only: bool,
+    pub limits: BetLimits
+}
+
+impl RuleSet {
+    /// Returns the built-in tables with their names
+    pub fn presets() -> Vec<(&'static str, RuleSet)> {
+        vec![
+            ("Las Vegas Strip", RuleSet::default()),
+            ("Downtown double deck", RuleSet {
+                dealer_hits_soft_17: true,
+                decks: 2,
+                penetration: 0.65,
+                limits: BetLimits::from_dollars(2, 200, 1),
+                ..RuleSet::default()
+            }),
+            ("Low limit 6:5", RuleSet {
+                dealer_hits_soft_17: true,
+                blackjack_payout: BlackjackPayout::SixToFive,
+                double_rule: DoubleRule::TenToEleven,
+                double_after_split: false,
+                limits: BetLimits {
+                    min: Money::from_cents(50),
+                    max: Money::from_cents(10000),
+                    increment: Money::from_cents(50)
+                },
+                ..RuleSet::default()
+            }),
+            ("Atlantic City", RuleSet {
+                decks: 8,
+                surrender: SurrenderRule::Late,
+                limits: BetLimits::from_dollars(5, 1000, 5),
+                ..RuleSet::default()
+            }),
+            ("No peek", RuleSet {
+                surrender: SurrenderRule::Late,
+                hole_card: HoleCardRule::NoPeek,
+                ..RuleSet::default()
+            }),
+            ("European", RuleSet {
+                double_rule: DoubleRule::NineToEleven,
+                hole_card: HoleCardRule::NoHoleCard,
+                ..RuleSet::default()
+            }),
+            ("European OBO", RuleSet {
+                surrender: SurrenderRule::Early,
+                hole_card: HoleCardRule::NoHoleCard,
+                original_bets_only: true,
+                ..RuleSet::default()
+            }),
+            ("Classic single deck", RuleSet {
+                blackjack_payout: BlackjackPayout::EvenMoney,
+                double_after_split: false,
+                max_split_hands: 2,
+                decks: 1,
+                penetration: 0.5,
+                limits: BetLimits::from_dollars(5, 200, 1),
+                ..RuleSet::default()
+            }),
+        ]
+    }
+
+    /// Returns the rules of the built-in table with the passed name
+    #[cfg(test)]
+    pub(crate) fn preset(name: &str) -> RuleSet {
+        RuleSet::presets().into_iter().find(|(preset, _)| *preset == name).unwrap().1
+    }
+}
+
+impl Default for RuleSet {
+    /// Six deck game where the dealer stands on soft 17 and naturals pay 3:2
+    fn default() -> Self {
+        RuleSet {
+            dealer_hits_soft_17: false,
+            blackjack_payout: BlackjackPayout::ThreeToTwo,
+            double_rule: DoubleRule::AnyTwo,
+            double_after_split: true,
+            max_split_hands: 4,
+            hit_split_aces: false,
+            decks: 6,
+            penetration: 0.75,
+            surrender: SurrenderRule::NoSurrender,
+            hole_card: HoleCardRule::Peek,
+            original_bets_only: false,
+            limits: BetLimits::default()
+        }
+    }
+}
+
+impl fmt::Display for RuleSet {
+    /// Formats the rules as a single line, e.g. "6 decks | S17 | BJ pays 3:2 | ..."
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decks = if self.decks == 1 { String::from("1 deck") } else { format!("{} decks", self.decks) };
+        let soft_17 = if self.dealer_hits_soft_17 { "H17" } else { "S17" };
+        let das = if self.double_after_split { "DAS" } else { "No DAS" };
+        let split_aces = if self.hit_split_aces { "Hit split aces" } else { "One card to split aces" };
+        let obo = if self.original_bets_only && self.hole_card != HoleCardRule::Peek { " (OBO)" } else { "" };
+
+        write!(
+            f,
+            "{decks} | {soft_17} | BJ pays {} | {} | {das} | Split to {} hands | {split_aces} | {} | {}{obo} | {}",
+            self.blackjack_payout, self.double_rule, self.max_split_hands, self.surrender, self.hole_card, self.limits
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bet_limits_show_the_steps_bets_go_up_in() {
+        assert_eq!(BetLimits::from_dollars(5, 1000, 5).to_string(), "Bets $5-$1000 in steps of $5");
+        assert_eq!(BetLimits::default().to_string(), "Bets $1-$500 in steps of $1");
+        assert_eq!(BetLimits { increment: Money::from_cents(50), ..BetLimits::default() }.to_string(), "Bets $1-$500 in steps of $0.50");
+        assert_eq!(BetLimits { increment: Money::from_cents(1), ..BetLimits::default() }.to_string(), "Bets $1-$500");
+        assert_eq!(BetLimits { increment: Money::ZERO, ..BetLimits::default() }.to_string(), "Bets $1-$500");
+    }
+}
@@ -1,59 +1,877 @@
-use std::io::{Write, Read};
-use std::fs::File;
+use std::cmp::Reverse;
+use std::env;
+use std::fmt;
+use std::io::{self, Write, Read};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use bincode::{serialize, deserialize, Error};
+use bincode::{serialize, serialized_size, deserialize, Error};
 
 use serde::{Serialize, Deserialize};
 
-/// Serializes and writes player data to a file. If file doesn't exist, new one is created.
-/// 
+use blackjack::card::{Rank, Suit};
+use blackjack::count::DealtCards;
+use blackjack::money::Money;
+use blackjack::round::{Round, Table};
+use blackjack::rules::{BetLimits, BlackjackPayout, DoubleRule, HoleCardRule, RuleSet, SurrenderRule};
+use blackjack::shoe::Shoe;
+
+use crate::drill::DrillStats;
+use crate::review::SessionReview;
+
+// Every save file starts with these bytes followed by the format version as a little-endian u16
+const SAVE_MAGIC: &[u8; 4] = b"BJSV";
+pub const SAVE_VERSION: u16 = 9;
+
+// Every profile is saved to its own file in the save directory. The file is written to a temporary file first
+// and the previous save is kept as a backup
+const SAVE_EXTENSION: &str = "blackjack";
+const TEMP_EXTENSION: &str = "blackjack.tmp";
+const BACKUP_EXTENSION: &str = "blackjack.bak";
+
+// Every finished round is written to the profile's hand history file
+const HISTORY_EXTENSION: &str = "history";
+
+// Environment variable that overrides the default save directory
+pub const SAVE_DIR_ENV: &str = "BLACKJACK_DATA_DIR";
+
+// Directory where profiles are saved. It's resolved when it's first needed unless set before that
+static SAVE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// The only save file used before profiles
+const SINGLE_SAVE_FILE: &str = "save.blackjack";
+
+/// Enum for everything that can go wrong when loading a save
+#[derive(Debug)]
+pub enum LoadError {
+    Missing,
+    Unreadable(io::Error),
+    Corrupt,
+    TooNew(u16)
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Missing => write!(f, "Couldn't find save file"),
+            LoadError::Unreadable(err) => write!(f, "Failed to read save file: {err}"),
+            LoadError::Corrupt => write!(f, "Deserialization failed. Save file is corrupted"),
+            LoadError::TooNew(version) => write!(f, "Save file is from a newer version of the game (format {version}). Update the game to load it")
+        }
+    }
+}
+
+/// Sets the directory where profiles are saved. It has to be called before anything is saved or loaded
+///
+/// # Returns
+///
+/// Ok or an Err if the directory was already in use
+pub fn set_save_dir(dir: PathBuf) -> Result<(), String> {
+    SAVE_DIR.set(dir).map_err(|_| String::from("Save directory is already in use"))
+}
+
+/// Returns the directory where profiles are saved
+pub fn save_dir() -> &'static Path {
+    SAVE_DIR.get_or_init(default_save_dir)
+}
+
+/// Returns the per-user data directory of the game. The BLACKJACK_DATA_DIR environment variable overrides it.
+/// Otherwise it's %APPDATA%\blackjack on Windows, ~/Library/Application Support/blackjack on macOS and
+/// $XDG_DATA_HOME/blackjack or ~/.local/share/blackjack elsewhere
+fn default_save_dir() -> PathBuf {
+    if let Some(dir) = env::var_os(SAVE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
+    let home = env::var_os("HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from);
+
+    let data_dir = if cfg!(windows) {
+        env::var_os("APPDATA").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home.map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home.map(|home| home.join(".local").join("share")))
+    };
+
+    // If no data directory can be found, fall back to the directory the game was started in
+    match data_dir {
+        Some(dir) => dir.join("blackjack"),
+        None => PathBuf::from("saves")
+    }
+}
+
+/// Returns the path of the save file of the profile with the passed id
+fn profile_path(id: &str) -> PathBuf {
+    save_dir().join(format!("{id}.{SAVE_EXTENSION}"))
+}
+
+/// Returns the path of the backup of the profile's previous save
+fn backup_path(id: &str) -> PathBuf {
+    save_dir().join(format!("{id}.{BACKUP_EXTENSION}"))
+}
+
+/// Returns the path of the profile's hand history
+pub fn history_path(id: &str) -> PathBuf {
+    save_dir().join(format!("{id}.{HISTORY_EXTENSION}"))
+}
+
+/// Writes data to a temporary file first and then moves it over the save file, so that a crash in the middle
+/// of writing never leaves a half written save behind. The previous save is kept as a backup
+///
+/// # Returns
+///
+/// Ok or Err if any of the file operations failed
+fn write_atomically(id: &str, data: &[u8]) -> io::Result<()> {
+    let path = profile_path(id);
+    let temp_path = save_dir().join(format!("{id}.{TEMP_EXTENSION}"));
+
+    // Write the whole save to the temporary file and make sure it's on the disk
+    let mut file = File::create(&temp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+
+    // The current save becomes the backup and the temporary file becomes the save
+    if path.exists() {
+        fs::rename(&path, backup_path(id))?;
+    }
+    fs::rename(&temp_path, &path)
+}
+
+/// Returns the current time as seconds since the Unix epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+/// Serializes and writes player data to the profile's save file. If file doesn't exist, new one is created.
+/// The last played time of the player is updated too
+///
+/// # Returns
+///
+/// Ok or Err if serialization failed, file creation failed or writing to file failed
+pub fn save(player: &mut Player) -> Result<(), Error> {
+    player.last_played = unix_time();
+
+    write_player(player)
+}
+
+/// Serializes and writes player data to the profile's save file as it is
+///
 /// # Returns
-/// 
+///
 /// Ok or Err if serialization failed, file creation failed or writing to file failed
-pub fn save(player: &Player) -> Result<(), Error> {
-    // Serialize player to a binary format
-    let encoded: Vec<u8> = serialize(player)?;
+fn write_player(player: &Player) -> Result<(), Error> {
+    // Header first, then the player in a binary format
+    let mut encoded: Vec<u8> = Vec::new();
+    encoded.extend_from_slice(SAVE_MAGIC);
+    encoded.extend_from_slice(&SAVE_VERSION.to_le_bytes());
+    encoded.extend(serialize(player)?);
 
     // Write binary data to a file
-    let mut file = File::create("save.blackjack")?;
-    file.write_all(&encoded)?;
+    fs::create_dir_all(save_dir())?;
+    write_atomically(&player.id, &encoded)?;
 
     Ok(())
 }
 
-/// Reads the save file and deserializes player object
-/// 
+/// Reads the save file of the profile with the passed id and deserializes player object.
+/// If the save is corrupted or missing, the backup of the previous save is used. Saves from older versions are upgraded
+///
+/// # Returns
+///
+/// Ok containing player object or LoadError if file doesn't exist, failed to read file or failed to deserialize data
+pub fn load(id: &str) -> Result<Player, LoadError> {
+    let (player, upgraded) = read_profile(id)?;
+
+    // Upgrade the file right away without changing when it was last played. If it fails, the game saves again at
+    // the start of the next round anyway
+    if upgraded {
+        let _ = write_player(&player);
+    }
+
+    Ok(player)
+}
+
+/// Reads the profile with the passed id like load, but never writes the file
+///
 /// # Returns
-/// 
-/// Ok containing player object or Err if file doesn't exist, failed to read file or failed to deserialize data
-pub fn load() -> Result<Player, String> {
-    let mut file = match File::open("save.blackjack") {
+///
+/// Ok containing player object and true if it was upgraded from an older version, or LoadError
+fn read_profile(id: &str) -> Result<(Player, bool), LoadError> {
+    let (mut player, upgraded) = match read_file(&profile_path(id)).and_then(|buffer| decode(&buffer)) {
+        Ok(loaded) => loaded,
+        Err(err @ (LoadError::Corrupt | LoadError::Missing)) => {
+            // The error of the actual save is more useful than the backup's error
+            read_file(&backup_path(id)).and_then(|buffer| decode(&buffer)).map_err(|_| err)?
+        },
+        Err(err) => return Err(err)
+    };
+    player.id = String::from(id);
+
+    Ok((player, upgraded))
+}
+
+/// Loads every profile in the save directory, sorted so that the most recently played comes first. Profiles from
+/// older versions are upgraded only in memory, so listing them doesn't change any file
+///
+/// # Returns
+///
+/// Vec of profile ids with their player objects or the reason they couldn't be loaded
+pub fn list_profiles() -> Vec<(String, Result<Player, LoadError>)> {
+    let entries = match fs::read_dir(save_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new()
+    };
+
+    // Every save file is a profile and its name is the id. A backup alone counts too in case the game
+    // crashed between replacing the save and moving the new one in place
+    let mut ids: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|file_name| {
+            let id = file_name
+                .strip_suffix(&format!(".{SAVE_EXTENSION}"))
+                .or_else(|| file_name.strip_suffix(&format!(".{BACKUP_EXTENSION}")))?;
+            Some(String::from(id))
+        })
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut profiles: Vec<(String, Result<Player, LoadError>)> = ids
+        .into_iter()
+        .map(|id| {
+            let player = read_profile(&id).map(|(player, _)| player);
+            (id, player)
+        })
+        .collect();
+
+    profiles.sort_by_key(|(id, player)| {
+        let last_played = player.as_ref().map(|player| player.last_played).unwrap_or(0);
+        (Reverse(last_played), id.clone())
+    });
+
+    profiles
+}
+
+/// Deletes the save file of the profile with the passed id, its backup and its hand history
+///
+/// # Returns
+///
+/// Ok or Err if the file couldn't be removed
+pub fn delete_profile(id: &str) -> io::Result<()> {
+    fs::remove_file(profile_path(id))?;
+
+    // The profile is gone even if the backup or the history can't be removed
+    let _ = fs::remove_file(backup_path(id));
+    let _ = fs::remove_file(history_path(id));
+
+    Ok(())
+}
+
+/// Creates a file friendly id from a player name that isn't used by any other profile yet
+pub fn new_profile_id(name: &str) -> String {
+    // Keep letters and numbers and replace everything else with dashes
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+    let slug = if slug.is_empty() { String::from("player") } else { slug };
+
+    // Add a number to the end if the id is taken
+    let mut id = slug.clone();
+    let mut number = 2;
+    while profile_path(&id).exists() {
+        id = format!("{slug}-{number}");
+        number += 1;
+    }
+
+    id
+}
+
+/// Moves the single save file used before profiles into the save directory as a new profile
+///
+/// # Returns
+///
+/// Ok containing the name of the imported player if there was a save to import, or LoadError if it couldn't be read
+pub fn import_single_save() -> Result<Option<String>, LoadError> {
+    let path = Path::new(SINGLE_SAVE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let buffer = read_file(path)?;
+    let (mut player, _) = decode(&buffer)?;
+    player.id = new_profile_id(&player.name);
+
+    save(&mut player).map_err(|_| LoadError::Unreadable(io::Error::other("Couldn't write the imported profile")))?;
+
+    // Keep the old file around under a new name instead of deleting it
+    fs::rename(path, format!("{SINGLE_SAVE_FILE}.imported")).map_err(LoadError::Unreadable)?;
+
+    Ok(Some(player.name))
+}
+
+/// Reads a whole file to a buffer
+fn read_file(path: &Path) -> Result<Vec<u8>, LoadError> {
+    let mut file = match File::open(path) {
         Ok(f) => f,
-        Err(_) => {
-            return Err(String::from("Couldn't find save file"));
-        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(LoadError::Missing),
+        Err(err) => return Err(LoadError::Unreadable(err))
     };
     let mut buffer = Vec::new();
-    match file.read_to_end(&mut buffer) {
-        Ok(_) => {},
-        Err(_) => {
-            return Err(String::from("Failed to read save file"));
-        }
+    if let Err(err) = file.read_to_end(&mut buffer) {
+        return Err(LoadError::Unreadable(err));
+    }
+
+    Ok(buffer)
+}
+
+/// Deserializes save data of any known format version
+///
+/// # Returns
+///
+/// Ok containing the player object and true if it was upgraded from an older version, or LoadError
+fn decode(buffer: &[u8]) -> Result<(Player, bool), LoadError> {
+    // Files without the header are from before the save format was versioned
+    let Some(versioned) = buffer.strip_prefix(SAVE_MAGIC) else {
+        return Ok((migrate_legacy(buffer)?, true));
     };
-    
-    let player: Player = match deserialize(&buffer) {
-        Ok(p) => p,
-        Err(_) => {
-            return Err(String::from("Deserialization failed. Save file is corrupted"))
+
+    // Read the format version
+    if versioned.len() < 2 {
+        return Err(LoadError::Corrupt);
+    }
+    let (version, data) = versioned.split_at(2);
+    let version = u16::from_le_bytes([version[0], version[1]]);
+
+    match version {
+        SAVE_VERSION => Ok((deserialize(data).map_err(|_| LoadError::Corrupt)?, false)),
+        8 => Ok((migrate_v8(data)?, true)),
+        7 => Ok((migrate_v7(data)?, true)),
+        6 => Ok((migrate_v6(data)?, true)),
+        5 => Ok((migrate_v5(data)?, true)),
+        4 => Ok((migrate_v4(data)?, true)),
+        3 => Ok((migrate_v3(data)?, true)),
+        2 => Ok((migrate_v2(data)?, true)),
+        version if version > SAVE_VERSION => Err(LoadError::TooNew(version)),
+        _ => Err(LoadError::Corrupt)
+    }
+}
+
+/// Player data as it was saved before the save file had a header. Wealth was in whole dollars
+#[derive(Serialize, Deserialize)]
+struct LegacyPlayer {
+    name: String,
+    wealth: u16
+}
+
+/// Reads a save file that was written before the save format was versioned
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data isn't a legacy save
+fn migrate_legacy(buffer: &[u8]) -> Result<Player, LoadError> {
+    let legacy: LegacyPlayer = deserialize(buffer).map_err(|_| LoadError::Corrupt)?;
+
+    // Deserializing ignores extra bytes, so make sure the whole file was a legacy save
+    if serialized_size(&legacy).map_err(|_| LoadError::Corrupt)? != buffer.len() as u64 {
+        return Err(LoadError::Corrupt);
+    }
+
+    Ok(Player {
+        id: String::new(),
+        name: legacy.name,
+        wealth: Money::from_dollars(legacy.wealth as u64).ok_or(LoadError::Corrupt)?,
+        last_played: 0,
+        table: None,
+        drill: DrillStats::new(),
+        last_session: SessionReview::default()
+    })
+}
+
+/// Player data as it was saved in format version 2, before profiles
+#[derive(Serialize, Deserialize)]
+struct PlayerV2 {
+    name: String,
+    wealth: Money
+}
+
+/// Reads player data saved in format version 2
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data is corrupted
+fn migrate_v2(data: &[u8]) -> Result<Player, LoadError> {
+    let old: PlayerV2 = deserialize(data).map_err(|_| LoadError::Corrupt)?;
+
+    Ok(Player {
+        id: String::new(),
+        name: old.name,
+        wealth: old.wealth,
+        last_played: 0,
+        table: None,
+        drill: DrillStats::new(),
+        last_session: SessionReview::default()
+    })
+}
+
+/// Player data as it was saved in format version 3, before the table was saved
+#[derive(Serialize, Deserialize)]
+struct PlayerV3 {
+    name: String,
+    wealth: Money,
+    last_played: u64
+}
+
+/// Reads player data saved in format version 3
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data is corrupted
+fn migrate_v3(data: &[u8]) -> Result<Player, LoadError> {
+    let old: PlayerV3 = deserialize(data).map_err(|_| LoadError::Corrupt)?;
+
+    Ok(Player {
+        id: String::new(),
+        name: old.name,
+        wealth: old.wealth,
+        last_played: old.last_played,
+        table: None,
+        drill: DrillStats::new(),
+        last_session: SessionReview::default()
+    })
+}
+
+/// Table as it was saved in format version 4, before shuffles were seeded
+#[derive(Serialize, Deserialize)]
+struct TableV4 {
+    rules: RuleSetV5,
+    shoe: Shoe,
+    round: Option<Round>
+}
+
+/// Player data as it was saved in format version 4
+#[derive(Serialize, Deserialize)]
+struct PlayerV4 {
+    name: String,
+    wealth: Money,
+    last_played: u64,
+    table: Option<TableV4>
+}
+
+/// Reads player data saved in format version 4. The table keeps its shoe and round and gets a new random seed
+/// for the next shuffles
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data is corrupted
+fn migrate_v4(data: &[u8]) -> Result<Player, LoadError> {
+    let old: PlayerV4 = deserialize(data).map_err(|_| LoadError::Corrupt)?;
+
+    Ok(Player {
+        id: String::new(),
+        name: old.name,
+        wealth: old.wealth,
+        last_played: old.last_played,
+        table: old.table.map(|table| TableV8 {
+            rules: table.rules.into(),
+            shoe: table.shoe,
+            round: table.round,
+            seed: rand::random(),
+            shuffles: 0
+        }.into()),
+        drill: DrillStats::new(),
+        last_session: SessionReview::default()
+    })
+}
+
+/// Table rules as they were saved in format versions 4 and 5, before bet limits
+#[derive(Serialize, Deserialize)]
+struct RuleSetV5 {
+    dealer_hits_soft_17: bool,
+    blackjack_payout: BlackjackPayout,
+    double_rule: DoubleRule,
+    double_after_split: bool,
+    max_split_hands: u8,
+    hit_split_aces: bool,
+    decks: u8,
+    penetration: f32,
+    surrender: SurrenderRule,
+    hole_card: HoleCardRule,
+    original_bets_only: bool
+}
+
+impl From<RuleSetV5> for RuleSet {
+    /// Tables from before bet limits get the default limits
+    fn from(old: RuleSetV5) -> Self {
+        RuleSet {
+            dealer_hits_soft_17: old.dealer_hits_soft_17,
+            blackjack_payout: old.blackjack_payout,
+            double_rule: old.double_rule,
+            double_after_split: old.double_after_split,
+            max_split_hands: old.max_split_hands,
+            hit_split_aces: old.hit_split_aces,
+            decks: old.decks,
+            penetration: old.penetration,
+            surrender: old.surrender,
+            hole_card: old.hole_card,
+            original_bets_only: old.original_bets_only,
+            limits: BetLimits::default()
         }
-    };
-    
-    Ok(player)
+    }
+}
+
+/// Table as it was saved in format version 5
+#[derive(Serialize, Deserialize)]
+struct TableV5 {
+    rules: RuleSetV5,
+    shoe: Shoe,
+    round: Option<Round>,
+    seed: u64,
+    shuffles: u32
+}
+
+/// Player data as it was saved in format version 5
+#[derive(Serialize, Deserialize)]
+struct PlayerV5 {
+    name: String,
+    wealth: Money,
+    last_played: u64,
+    table: Option<TableV5>
+}
+
+/// Reads player data saved in format version 5. The table gets the default bet limits
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data is corrupted
+fn migrate_v5(data: &[u8]) -> Result<Player, LoadError> {
+    let old: PlayerV5 = deserialize(data).map_err(|_| LoadError::Corrupt)?;
+
+    Ok(Player {
+        id: String::new(),
+        name: old.name,
+        wealth: old.wealth,
+        last_played: old.last_played,
+        table: old.table.map(|table| TableV8 {
+            rules: table.rules.into(),
+            shoe: table.shoe,
+            round: table.round,
+            seed: table.seed,
+            shuffles: table.shuffles
+        }.into()),
+        drill: DrillStats::new(),
+        last_session: SessionReview::default()
+    })
+}
+
+/// Player data as it was saved in format version 6, before the drill
+#[derive(Serialize, Deserialize)]
+struct PlayerV6 {
+    name: String,
+    wealth: Money,
+    last_played: u64,
+    table: Option<TableV8>
+}
+
+/// Reads player data saved in format version 6
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data is corrupted
+fn migrate_v6(data: &[u8]) -> Result<Player, LoadError> {
+    let old: PlayerV6 = deserialize(data).map_err(|_| LoadError::Corrupt)?;
+
+    Ok(Player {
+        id: String::new(),
+        name: old.name,
+        wealth: old.wealth,
+        last_played: old.last_played,
+        table: old.table.map(Table::from),
+        drill: DrillStats::new(),
+        last_session: SessionReview::default()
+    })
+}
+
+/// Player data as it was saved in format version 7, before the session review
+#[derive(Serialize, Deserialize)]
+struct PlayerV7 {
+    name: String,
+    wealth: Money,
+    last_played: u64,
+    table: Option<TableV8>,
+    drill: DrillStats
+}
+
+/// Reads player data saved in format version 7
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data is corrupted
+fn migrate_v7(data: &[u8]) -> Result<Player, LoadError> {
+    let old: PlayerV7 = deserialize(data).map_err(|_| LoadError::Corrupt)?;
+
+    Ok(Player {
+        id: String::new(),
+        name: old.name,
+        wealth: old.wealth,
+        last_played: old.last_played,
+        table: old.table.map(Table::from),
+        drill: old.drill,
+        last_session: SessionReview::default()
+    })
+}
+
+/// Table as it was saved in format versions 6 to 8, before the dealt cards were recorded
+#[derive(Serialize, Deserialize)]
+struct TableV8 {
+    rules: RuleSet,
+    shoe: Shoe,
+    round: Option<Round>,
+    seed: u64,
+    shuffles: u32
+}
+
+impl From<TableV8> for Table {
+    /// The dealt cards are worked out from the cards left in the shoe. The burned card can't be told apart from
+    /// them, so it's counted as dealt
+    fn from(old: TableV8) -> Self {
+        // A shoe that hasn't been shuffled yet is empty and nothing has been dealt from it
+        let mut dealt = DealtCards::default();
+        if old.shoe.cards_remaining() > 0 {
+            let in_full_shoe = old.shoe.decks() as usize * Suit::ALL.len();
+            for rank in Rank::ALL {
+                for _ in old.shoe.remaining_of(rank)..in_full_shoe {
+                    dealt.add(rank);
+                }
+            }
+        }
+
+        Table {
+            rules: old.rules,
+            shoe: old.shoe,
+            round: old.round,
+            seed: old.seed,
+            shuffles: old.shuffles,
+            dealt
+        }
+    }
+}
+
+/// Player data as it was saved in format version 8, before the dealt cards were recorded
+#[derive(Serialize, Deserialize)]
+struct PlayerV8 {
+    name: String,
+    wealth: Money,
+    last_played: u64,
+    table: Option<TableV8>,
+    drill: DrillStats,
+    last_session: SessionReview
+}
+
+/// Reads player data saved in format version 8
+///
+/// # Returns
+///
+/// Ok containing the upgraded player object or LoadError if the data is corrupted
+fn migrate_v8(data: &[u8]) -> Result<Player, LoadError> {
+    let old: PlayerV8 = deserialize(data).map_err(|_| LoadError::Corrupt)?;
+
+    Ok(Player {
+        id: String::new(),
+        name: old.name,
+        wealth: old.wealth,
+        last_played: old.last_played,
+        table: old.table.map(Table::from),
+        drill: old.drill,
+        last_session: old.last_session
+    })
 }
 
-/// Player struct
+/// Player struct. Every player is a profile with its own save file
 #[derive(Serialize, Deserialize)]
 pub struct Player {
+    /// Name of the profile's save file. It is not saved inside the file, so it stays the same when renaming
+    #[serde(skip)]
+    pub id: String,
     pub name: String,
-    pub wealth: u16
-}
\ No newline at end of file
+    pub wealth: Money,
+    /// Seconds since the Unix epoch, or 0 if unknown
+    pub last_played: u64,
+    /// Table the player left, including the round in progress if the game was quit in the middle of it
+    pub table: Option<Table>,
+    /// Accuracy of every situation practiced in the drill
+    pub drill: DrillStats,
+    /// Decisions of the last game session compared to basic strategy
+    pub last_session: SessionReview
+}
+
+impl Player {
+    /// Creates a new profile with the starting wealth of $10
+    pub fn new(name: String) -> Player {
+        Player {
+            id: new_profile_id(&name),
+            name,
+            wealth: Money::from_cents(1000),
+            last_played: 0,
+            table: None,
+            drill: DrillStats::new(),
+            last_session: SessionReview::default()
+        }
+    }
+}
+
+/// Points the save directory to an empty directory of this test run. Tests that touch any file call this first
+#[cfg(test)]
+pub fn use_test_dir() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        let dir = env::temp_dir().join(format!("blackjack-tests-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        set_save_dir(dir).unwrap();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::mock::StepRng;
+
+    /// Returns the save file of the passed format version holding the passed data
+    fn encode<T: Serialize>(version: u16, data: &T) -> Vec<u8> {
+        let mut encoded = SAVE_MAGIC.to_vec();
+        encoded.extend_from_slice(&version.to_le_bytes());
+        encoded.extend(serialize(data).unwrap());
+        encoded
+    }
+
+    /// Returns the player of the save data, failing the test if it doesn't load
+    fn decode_player(buffer: &[u8]) -> (Player, bool) {
+        match decode(buffer) {
+            Ok(decoded) => decoded,
+            Err(err) => panic!("{err}")
+        }
+    }
+
+    #[test]
+    fn legacy_saves_are_read_in_whole_dollars() {
+        let (player, upgraded) = decode_player(&serialize(&LegacyPlayer { name: String::from("Old"), wealth: 250 }).unwrap());
+
+        assert!(upgraded);
+        assert_eq!(player.name, "Old");
+        assert_eq!(player.wealth, Money::from_cents(25_000));
+        assert_eq!(player.last_played, 0);
+    }
+
+    #[test]
+    fn every_old_version_is_upgraded() {
+        let (player, upgraded) = decode_player(&encode(2, &PlayerV2 { name: String::from("Two"), wealth: Money::from_cents(1050) }));
+        assert!(upgraded);
+        assert_eq!((player.name.as_str(), player.wealth, player.last_played), ("Two", Money::from_cents(1050), 0));
+
+        let (player, _) = decode_player(&encode(3, &PlayerV3 { name: String::from("Three"), wealth: Money::from_cents(700), last_played: 42 }));
+        assert_eq!((player.name.as_str(), player.last_played), ("Three", 42));
+        assert!(player.table.is_none());
+
+        // Tables from before bet limits get the default ones and keep their seed
+        let rules = RuleSetV5 {
+            dealer_hits_soft_17: true,
+            blackjack_payout: BlackjackPayout::SixToFive,
+            double_rule: DoubleRule::TenToEleven,
+            double_after_split: false,
+            max_split_hands: 2,
+            hit_split_aces: false,
+            decks: 2,
+            penetration: 0.5,
+            surrender: SurrenderRule::Late,
+            hole_card: HoleCardRule::Peek,
+            original_bets_only: false
+        };
+        let table = TableV5 { rules, shoe: Shoe::new(2, 0.5), round: None, seed: 99, shuffles: 3 };
+        let old = PlayerV5 { name: String::from("Five"), wealth: Money::from_cents(100), last_played: 7, table: Some(table) };
+        let (player, _) = decode_player(&encode(5, &old));
+        let table = player.table.unwrap();
+        assert_eq!((table.seed, table.shuffles), (99, 3));
+        assert_eq!(table.rules.blackjack_payout, BlackjackPayout::SixToFive);
+        assert_eq!(table.rules.limits, BetLimits::default());
+    }
+
+    #[test]
+    fn dealt_cards_are_worked_out_from_the_shoe_of_version_8() {
+        let mut shoe = Shoe::new(1, 0.75);
+        shoe.shuffle(&mut StepRng::new(0, 1));
+        for _ in 0..5 {
+            shoe.deal().unwrap();
+        }
+        let table = TableV8 { rules: RuleSet::default(), shoe, round: None, seed: 1, shuffles: 1 };
+        let old = PlayerV8 {
+            name: String::from("Eight"),
+            wealth: Money::from_cents(100),
+            last_played: 8,
+            table: Some(table),
+            drill: DrillStats::new(),
+            last_session: SessionReview::default()
+        };
+
+        let (player, upgraded) = decode_player(&encode(8, &old));
+        assert!(upgraded);
+        // The burned card is counted as dealt
+        assert_eq!(player.table.unwrap().dealt.total(), 6);
+    }
+
+    #[test]
+    fn current_saves_load_as_they_were_written() {
+        // New profiles look for a free id in the save directory
+        use_test_dir();
+        let mut player = Player::new(String::from("Now"));
+        player.last_played = 1_800_000_000;
+        player.table = Some(Table::new(RuleSet::default(), 5));
+
+        let (loaded, upgraded) = decode_player(&encode(SAVE_VERSION, &player));
+        assert!(!upgraded);
+        assert_eq!((loaded.name, loaded.wealth, loaded.last_played), (player.name, player.wealth, player.last_played));
+        assert_eq!(loaded.table.unwrap().seed, 5);
+    }
+
+    #[test]
+    fn unknown_data_isnt_loaded() {
+        assert!(matches!(decode(&encode(SAVE_VERSION + 1, &0u8)), Err(LoadError::TooNew(version)) if version == SAVE_VERSION + 1));
+        assert!(matches!(decode(&encode(1, &0u8)), Err(LoadError::Corrupt)));
+        assert!(matches!(decode(b"BJSV\x09"), Err(LoadError::Corrupt)));
+        assert!(matches!(decode(&encode(SAVE_VERSION, &0u8)), Err(LoadError::Corrupt)));
+        assert!(matches!(decode(b"not a save file at all"), Err(LoadError::Corrupt)));
+    }
+
+    #[test]
+    fn listing_an_old_profile_doesnt_rewrite_it() {
+        use_test_dir();
+        fs::create_dir_all(save_dir()).unwrap();
+        let old = encode(3, &PlayerV3 { name: String::from("Old"), wealth: Money::from_cents(900), last_played: 1_700_000_000 });
+        fs::write(profile_path("listed"), &old).unwrap();
+
+        let profiles = list_profiles();
+        let (_, player) = profiles.iter().find(|(id, _)| id == "listed").unwrap();
+        assert_eq!(player.as_ref().unwrap().last_played, 1_700_000_000);
+        assert_eq!(fs::read(profile_path("listed")).unwrap(), old);
+        assert!(!backup_path("listed").exists());
+
+        // Opening the profile upgrades the file, but it still hasn't been played
+        let player = load("listed").unwrap();
+        assert_eq!(player.last_played, 1_700_000_000);
+        let (player, upgraded) = read_profile("listed").unwrap();
+        assert!(!upgraded);
+        assert_eq!(player.last_played, 1_700_000_000);
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use blackjack::count::CountingSystem;
+
+use crate::input::InputSource;
+use crate::save::save_dir;
+use crate::systems::{load_systems, systems_dir};
+use crate::utils::{notification, pacing, set_pacing, NotificationDuration, Pacing};
+
+// Settings are shared by every profile and saved as "name = value" lines
+const SETTINGS_FILE: &str = "settings.txt";
+
+// Whether the running count and the true count are shown at the table
+static SHOW_COUNT: AtomicBool = AtomicBool::new(false);
+
+// System the count at the table is kept in. Hi-Lo until one is set
+static COUNTING_SYSTEM: RwLock<Option<CountingSystem>> = RwLock::new(None);
+
+/// Settings of the game
+pub struct Settings {
+    pub pacing: Pacing,
+    /// Show the count at the table for practicing card counting
+    pub show_count: bool,
+    /// Name of the counting system, built-in or custom
+    pub counting_system: String
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            pacing: Pacing::default(),
+            show_count: false,
+            counting_system: CountingSystem::default().name
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file. Settings that are missing or can't be read keep their default values
+    pub fn load() -> Settings {
+        let mut settings = Settings::default();
+        let Ok(file) = fs::read_to_string(save_dir().join(SETTINGS_FILE)) else {
+            return settings;
+        };
+
+        for line in file.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            match name.trim() {
+                "pacing" => settings.pacing = value.parse().unwrap_or_default(),
+                "show count" => settings.show_count = value.trim() == "on",
+                "counting system" => settings.counting_system = String::from(value.trim()),
+                _ => {}
+            };
+        }
+
+        settings
+    }
+
+    /// Writes the settings file
+    ///
+    /// # Returns
+    ///
+    /// Ok or Err if the file couldn't be written
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(save_dir())?;
+        let show_count = if self.show_count { "on" } else { "off" };
+        fs::write(
+            save_dir().join(SETTINGS_FILE),
+            format!("pacing = {}\nshow count = {show_count}\ncounting system = {}\n", self.pacing, self.counting_system)
+        )
+    }
+}
+
+/// Sets whether the count is shown at the table from now on
+pub fn set_show_count(show: bool) {
+    SHOW_COUNT.store(show, Ordering::Relaxed);
+}
+
+/// Returns true if the count is shown at the table
+pub fn show_count() -> bool {
+    SHOW_COUNT.load(Ordering::Relaxed)
+}
+
+/// Sets the system the count at the table is kept in from now on
+pub fn set_counting_system(system: CountingSystem) {
+    if let Ok(mut current) = COUNTING_SYSTEM.write() {
+        *current = Some(system);
+    }
+}
+
+/// Returns the system the count at the table is kept in
+pub fn counting_system() -> CountingSystem {
+    COUNTING_SYSTEM.read().ok().and_then(|current| current.clone()).unwrap_or_default()
+}
+
+/// Shows the count at the table or hides it if it's shown. The setting is saved right away
+///
+/// # Returns
+///
+/// true if the count is shown now
+pub fn toggle_count() -> bool {
+    let mut settings = Settings::load();
+    settings.show_count = !show_count();
+    set_show_count(settings.show_count);
+
+    if settings.save().is_err() {
+        notification("Couldn't save the settings", NotificationDuration::Long);
+    }
+
+    settings.show_count
+}
+
+/// Menu for changing the settings. Every change is saved right away
+pub fn settings_menu(input: &mut dyn InputSource) {
+    let mut settings = Settings::load();
+    settings.pacing = pacing();
+    settings.show_count = show_count();
+    settings.counting_system = counting_system().name;
+
+    loop {
+        println!("---");
+        println!("Settings");
+        println!("1. Pacing: {}", settings.pacing);
+        println!("2. Show the count: {}", if settings.show_count { "on" } else { "off" });
+        println!("3. Counting system: {}", settings.counting_system);
+        println!("4. Back");
+
+        let Some(line) = input.read_line() else {
+            return;
+        };
+
+        match line.parse::<u8>() {
+            Ok(1) => {
+                settings.pacing = settings.pacing.next();
+                set_pacing(settings.pacing);
+            },
+            Ok(2) => {
+                settings.show_count = !settings.show_count;
+                set_show_count(settings.show_count);
+            },
+            Ok(3) => {
+                let Some(system) = pick_counting_system(input) else {
+                    continue;
+                };
+                settings.counting_system = system.name.clone();
+                set_counting_system(system);
+            },
+            Ok(4) => return,
+            Ok(num) => {
+                notification(&format!("No option for number {num}"), NotificationDuration::Short);
+                continue;
+            },
+            Err(_) => {
+                notification("Input must be a number", NotificationDuration::Short);
+                continue;
+            }
+        };
+
+        if settings.save().is_err() {
+            notification("Couldn't save the settings", NotificationDuration::Long);
+        }
+    }
+}
+
+/// Lets the player choose one of the built-in or custom counting systems. Custom files that can't be read are listed
+/// with the reason
+///
+/// # Returns
+///
+/// Chosen system or None if the player went back or the input ended
+fn pick_counting_system(input: &mut dyn InputSource) -> Option<CountingSystem> {
+    let (systems, errors) = load_systems();
+
+    println!("---");
+    println!("Counting systems");
+    for (index, system) in systems.iter().enumerate() {
+        println!("{}. {system}", index + 1);
+    }
+    println!("0. Back");
+    for err in errors {
+        println!("Couldn't read {err}");
+    }
+    println!("Add your own systems as .txt files to {}", systems_dir().display());
+
+    loop {
+        match input.read_line()?.parse::<usize>() {
+            Ok(0) => return None,
+            Ok(num) if num <= systems.len() => return Some(systems[num - 1].clone()),
+            Ok(num) => {
+                notification(&format!("No option for number {num}"), NotificationDuration::Short);
+            },
+            Err(_) => {
+                notification("Input must be a number", NotificationDuration::Short);
+            }
+        };
+    }
+}
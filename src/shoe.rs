@@ -0,0 +1,166 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::card::{new_deck, Card, Rank};
+
+// Share of the shoe dealt before the cut card at most. The rest is kept so that a round rarely runs out of cards
+const MAX_PENETRATION: f32 = 0.9;
+
+/// A dealing shoe holding one or more decks of cards.
+///
+/// A cut card is placed at the configured penetration when the shoe is shuffled. Once it comes
+/// out, the current round is finished normally and the shoe reports that it needs a reshuffle.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shoe {
+    cards: Vec<Card>,
+    decks: u8,
+    penetration: f32,
+    cut_card: usize,
+    cut_card_reached: bool
+}
+
+impl Shoe {
+    /// Creates a new empty shoe. The shoe needs to be shuffled before dealing from it
+    ///
+    /// `penetration` is the share of the shoe dealt before the cut card comes out, e.g. 0.75.
+    /// It is clamped to 0.1-0.9 and at least one deck is always used
+    pub fn new(decks: u8, penetration: f32) -> Shoe {
+        Shoe {
+            cards: Vec::new(),
+            decks: decks.max(1),
+            penetration: penetration.clamp(0.1, MAX_PENETRATION),
+            cut_card: 0,
+            cut_card_reached: true
+        }
+    }
+
+    /// Collects all cards back, shuffles them with the passed random number generator, places the cut card and
+    /// burns the top card. The same generator state always gives the same order
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        // Fill the shoe with every deck
+        self.cards.clear();
+        for _ in 0..self.decks {
+            self.cards.extend(new_deck());
+        }
+        self.cards.shuffle(rng);
+
+        // Place the cut card. Cards are dealt from the end of the vec, so the cut card is stored as
+        // the amount of cards that are left in the shoe when it comes out
+        let total = self.cards.len();
+        // Shoes saved before the limit may have a deeper penetration
+        let dealt_before_cut = (total as f32 * self.penetration.min(MAX_PENETRATION)).round() as usize;
+        self.cut_card = total - dealt_before_cut.min(total);
+        self.cut_card_reached = false;
+
+        // Burn the first card
+        self.cards.pop();
+    }
+
+    /// Shuffles every card except the passed ones that are still on the table back into the shoe. Used when the
+    /// shoe runs out in the middle of a round. The shoe still needs a full shuffle before the next round
+    pub fn shuffle_discards<R: Rng + ?Sized>(&mut self, in_play: &[Card], rng: &mut R) {
+        let mut cards = Vec::new();
+        for _ in 0..self.decks {
+            cards.extend(new_deck());
+        }
+        for card in in_play {
+            if let Some(index) = cards.iter().position(|other| other == card) {
+                cards.swap_remove(index);
+            }
+        }
+        cards.shuffle(rng);
+
+        self.cards = cards;
+        self.cut_card = 0;
+        self.cut_card_reached = true;
+    }
+
+    /// Creates a shoe of one deck that deals the passed cards in order. The cut card never comes out
+    #[cfg(test)]
+    pub(crate) fn stacked(cards: &[Card]) -> Shoe {
+        Shoe {
+            cards: cards.iter().rev().copied().collect(),
+            decks: 1,
+            penetration: MAX_PENETRATION,
+            cut_card: 0,
+            cut_card_reached: false
+        }
+    }
+
+    /// Deals the top card of the shoe
+    ///
+    /// # Returns
+    ///
+    /// Ok containing the card or an Err if the shoe is empty
+    pub fn deal(&mut self) -> Result<Card, String> {
+        let card = match self.cards.pop() {
+            Some(card) => card,
+            None => return Err(String::from("Shoe is empty, cannot deal cards"))
+        };
+
+        if self.cards.len() <= self.cut_card {
+            self.cut_card_reached = true;
+        }
+
+        Ok(card)
+    }
+
+    /// Returns true if the cut card has come out and the shoe should be shuffled before the next round
+    pub fn needs_shuffle(&self) -> bool {
+        self.cut_card_reached
+    }
+
+    /// Returns the amount of cards left in the shoe
+    pub fn cards_remaining(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Returns the amount of cards of the rank left in the shoe
+    pub fn remaining_of(&self, rank: Rank) -> usize {
+        self.cards.iter().filter(|card| card.rank == rank).count()
+    }
+
+    /// Returns the amount of decks in the shoe
+    pub fn decks(&self) -> u8 {
+        self.decks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn cut_card_keeps_a_reserve() {
+        let mut shoe = Shoe::new(1, 1.0);
+        shoe.shuffle(&mut StepRng::new(0, 1));
+
+        let mut dealt = 0;
+        while !shoe.needs_shuffle() {
+            shoe.deal().unwrap();
+            dealt += 1;
+        }
+
+        // 47 of the 52 cards leave the shoe before the cut card, the first one burned
+        assert_eq!(dealt, 46);
+        assert_eq!(shoe.cards_remaining(), 5);
+    }
+
+    #[test]
+    fn discards_leave_out_the_cards_in_play() {
+        let mut shoe = Shoe::new(1, 0.75);
+        shoe.shuffle(&mut StepRng::new(0, 1));
+        let in_play: Vec<Card> = (0..3).map(|_| shoe.deal().unwrap()).collect();
+        while shoe.deal().is_ok() {}
+
+        shoe.shuffle_discards(&in_play, &mut StepRng::new(0, 1));
+
+        assert_eq!(shoe.cards_remaining(), 49);
+        assert!(shoe.needs_shuffle());
+        while let Ok(card) = shoe.deal() {
+            assert!(!in_play.contains(&card));
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+
+use crate::card::{evaluate, Card, Rank};
+use crate::engine::Action;
+use crate::money::Money;
+use crate::rules::{HoleCardRule, RuleSet, SurrenderRule};
+
+/// Enum for the kinds of hands basic strategy tells apart
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum HandKind {
+    /// Hand without an ace counted as 11, with its total
+    Hard(u8),
+    /// Hand with an ace counted as 11, with its total
+    Soft(u8),
+    /// Two cards of the same value. Ten, jack, queen and king are all a pair of tens
+    Pair(Rank)
+}
+
+impl HandKind {
+    /// Classifies the cards of a hand
+    pub fn of(cards: &[Card]) -> HandKind {
+        if let [first, second] = cards {
+            if first.rank.value() == second.rank.value() {
+                let rank = if first.rank.value() == 10 { Rank::Ten } else { first.rank };
+                return HandKind::Pair(rank);
+            }
+        }
+
+        let value = evaluate(cards);
+        match value.soft {
+            Some(soft) => HandKind::Soft(soft),
+            None => HandKind::Hard(value.hard)
+        }
+    }
+}
+
+impl fmt::Display for HandKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandKind::Hard(total) => write!(f, "hard {total}"),
+            HandKind::Soft(total) => write!(f, "soft {total}"),
+            HandKind::Pair(Rank::Ace) => write!(f, "pair of aces"),
+            HandKind::Pair(rank) => write!(f, "pair of {rank}s")
+        }
+    }
+}
+
+/// A decision basic strategy has an answer for: the kind of the player's hand and the dealer's upcard
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Situation {
+    pub hand: HandKind,
+    /// Ten, jack, queen and king are all a ten
+    pub upcard: Rank
+}
+
+impl Situation {
+    /// Creates the situation of the cards against the upcard
+    pub fn new(cards: &[Card], upcard: Rank) -> Situation {
+        let upcard = if upcard.value() == 10 { Rank::Ten } else { upcard };
+
+        Situation { hand: HandKind::of(cards), upcard }
+    }
+}
+
+impl fmt::Display for Situation {
+    /// Formats the situation, e.g. "soft 18 against 9"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} against {}", self.hand, self.upcard)
+    }
+}
+
+/// Plays the table rules allow for the hand right now, besides hitting and standing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub double: bool,
+    pub split: bool,
+    pub surrender: bool
+}
+
+/// Returns the basic strategy play for the hand against the dealer's upcard.
+///
+/// The strategy is the usual one for shoe games, adjusted for H17, DAS and surrender. Without a peek the
+/// player doesn't risk more against a ten or an ace, unless the table takes only the original bet. Aces are
+/// still split against a ten. Early surrender without a peek is decided by expected value instead of the chart
+///
+/// # Returns
+///
+/// Hit, Stand, DoubleDown, Split or Surrender. Only plays in `options` are returned besides hitting and standing
+pub fn basic_strategy(cards: &[Card], upcard: Rank, rules: &RuleSet, options: Options) -> Action {
+    let dealer = upcard.value();
+    let h17 = rules.dealer_hits_soft_17;
+
+    let kind = HandKind::of(cards);
+    let value = evaluate(cards);
+
+    // Doubles and splits against a possible unpeeked blackjack would lose the whole extra bet. Aces are still
+    // split against a ten, two hands starting with an ace are worth more than a soft 12 even then
+    let no_peek_risk = rules.hole_card != HoleCardRule::Peek && !rules.original_bets_only && dealer >= 10;
+    let aces_against_ten = kind == HandKind::Pair(Rank::Ace) && dealer == 10;
+    let options = Options {
+        double: options.double && !no_peek_risk,
+        split: options.split && (!no_peek_risk || aces_against_ten),
+        surrender: options.surrender && rules.surrender != SurrenderRule::NoSurrender
+    };
+
+    // Early surrender without a peek still saves half the bet against a natural, so the late surrender chart
+    // doesn't fit it
+    let surrender = if rules.surrender == SurrenderRule::Early && rules.hole_card != HoleCardRule::Peek {
+        options.surrender && should_surrender_early(cards, upcard, rules)
+    } else {
+        options.surrender && should_surrender(kind, dealer, h17)
+    };
+    if surrender {
+        return Action::Surrender;
+    }
+
+    if let HandKind::Pair(rank) = kind {
+        if options.split && should_split(rank, dealer, rules.double_after_split) {
+            return Action::Split;
+        }
+    }
+
+    match value.soft {
+        Some(soft) => play_soft(soft, dealer, h17, options.double),
+        None => play_hard(value.hard, dealer, h17, options.double)
+    }
+}
+
+/// Returns true if the hand should be given up once the dealer is known not to have a natural. This is late
+/// surrender, and also surrendering in the player's turn on a table with early surrender where the dealer has
+/// already peeked, since the early offer came before the peek
+fn should_surrender(kind: HandKind, dealer: u8, h17: bool) -> bool {
+    match kind {
+        HandKind::Hard(16) => dealer >= 9,
+        HandKind::Hard(15) => dealer == 10 || (h17 && dealer == 11),
+        HandKind::Hard(17) | HandKind::Pair(Rank::Eight) => h17 && dealer == 11,
+        _ => false
+    }
+}
+
+/// Returns true if the pair should be split
+fn should_split(rank: Rank, dealer: u8, das: bool) -> bool {
+    match rank {
+        Rank::Ace | Rank::Eight => true,
+        Rank::Nine => matches!(dealer, 2..=6 | 8 | 9),
+        Rank::Seven => dealer <= 7,
+        Rank::Six => (3..=6).contains(&dealer) || (das && dealer == 2),
+        Rank::Four => das && (5..=6).contains(&dealer),
+        Rank::Three | Rank::Two => (4..=7).contains(&dealer) || (das && dealer <= 3),
+        // Fives are played as a hard 10 and tens are a made 20
+        _ => false
+    }
+}
+
+/// Returns the play for a soft total
+fn play_soft(total: u8, dealer: u8, h17: bool, can_double: bool) -> Action {
+    let double_or = |otherwise: Action| if can_double { Action::DoubleDown } else { otherwise };
+
+    match total {
+        20.. => Action::Stand,
+        19 if h17 && dealer == 6 => double_or(Action::Stand),
+        19 => Action::Stand,
+        18 if (3..=6).contains(&dealer) || (h17 && dealer == 2) => double_or(Action::Stand),
+        18 if dealer <= 8 => Action::Stand,
+        17 if (3..=6).contains(&dealer) => double_or(Action::Hit),
+        15 | 16 if (4..=6).contains(&dealer) => double_or(Action::Hit),
+        13 | 14 if (5..=6).contains(&dealer) => double_or(Action::Hit),
+        _ => Action::Hit
+    }
+}
+
+/// Returns the play for a hard total
+fn play_hard(total: u8, dealer: u8, h17: bool, can_double: bool) -> Action {
+    let double_or = |otherwise: Action| if can_double { Action::DoubleDown } else { otherwise };
+
+    match total {
+        17.. => Action::Stand,
+        13..=16 if dealer <= 6 => Action::Stand,
+        12 if (4..=6).contains(&dealer) => Action::Stand,
+        11 if dealer <= 10 || h17 => double_or(Action::Hit),
+        10 if dealer <= 9 => double_or(Action::Hit),
+        9 if (3..=6).contains(&dealer) => double_or(Action::Hit),
+        _ => Action::Hit
+    }
+}
+
+// Card values from 2 to 11 where 11 is an ace
+const CARD_VALUES: [u8; 10] = [2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+/// Returns the chance of drawing a card of the passed value from an infinite deck
+fn card_chance(value: u8) -> f64 {
+    if value == 10 { 4.0 / 13.0 } else { 1.0 / 13.0 }
+}
+
+/// Returns the best total of a hand from its total with aces counted as 1, and if it's soft
+fn best_total(hard: u8, ace: bool) -> (u8, bool) {
+    if ace && hard + 10 <= 21 { (hard + 10, true) } else { (hard, false) }
+}
+
+/// Chances of the dealer's final hands
+#[derive(Default)]
+struct DealerOdds {
+    /// Chances of finishing at 17 to 21, not counting naturals
+    totals: [f64; 5],
+    bust: f64,
+    natural: f64
+}
+
+impl DealerOdds {
+    /// Calculates the odds for the upcard from an infinite deck. When the dealer peeks, the player only gets to
+    /// act if the dealer doesn't have a natural, so the odds are calculated without naturals
+    fn new(upcard: Rank, rules: &RuleSet) -> DealerOdds {
+        let mut odds = DealerOdds::default();
+        let value = upcard.value();
+        odds.draw(if value == 11 { 1 } else { value }, value == 11, 1, 1.0, rules);
+
+        if rules.hole_card == HoleCardRule::Peek && odds.natural > 0.0 {
+            let rest = 1.0 - odds.natural;
+            odds.totals.iter_mut().for_each(|chance| *chance /= rest);
+            odds.bust /= rest;
+            odds.natural = 0.0;
+        }
+
+        odds
+    }
+
+    /// Adds the chances of every way the dealer can finish from the passed hand
+    fn draw(&mut self, hard: u8, ace: bool, cards: u8, chance: f64, rules: &RuleSet) {
+        let (total, soft) = best_total(hard, ace);
+
+        if cards == 2 && total == 21 {
+            self.natural += chance;
+        } else if total > 21 {
+            self.bust += chance;
+        } else if total >= 17 && !(total == 17 && soft && rules.dealer_hits_soft_17) {
+            self.totals[(total - 17) as usize] += chance;
+        } else {
+            for value in CARD_VALUES {
+                let hard_value = if value == 11 { 1 } else { value };
+                self.draw(hard + hard_value, ace || value == 11, cards + 1, chance * card_chance(value), rules);
+            }
+        }
+    }
+
+    /// Returns the expected value of standing on the total against a dealer without a natural
+    fn stand(&self, total: u8) -> f64 {
+        let rest = 1.0 - self.natural;
+        let mut ev = self.bust / rest;
+        for (index, chance) in self.totals.iter().enumerate() {
+            let dealer_total = 17 + index as u8;
+            if total > dealer_total {
+                ev += chance / rest;
+            } else if total < dealer_total {
+                ev -= chance / rest;
+            }
+        }
+
+        ev
+    }
+}
+
+/// Expected values of playing on from a hand with the best play, keyed by the total with aces as 1 and if there's an ace
+struct HitTable<'a> {
+    dealer: &'a DealerOdds,
+    best: [[Option<f64>; 2]; 22]
+}
+
+impl HitTable<'_> {
+    /// Returns the expected value of taking a card and playing on the best way
+    fn hit(&mut self, hard: u8, ace: bool) -> f64 {
+        CARD_VALUES
+            .iter()
+            .map(|&value| {
+                let new_hard = hard + if value == 11 { 1 } else { value };
+                let ev = if new_hard > 21 { -1.0 } else { self.best(new_hard, ace || value == 11) };
+                card_chance(value) * ev
+            })
+            .sum()
+    }
+
+    /// Returns the expected value of the better of hitting and standing
+    fn best(&mut self, hard: u8, ace: bool) -> f64 {
+        if let Some(ev) = self.best[hard as usize][ace as usize] {
+            return ev;
+        }
+
+        let stand = self.dealer.stand(best_total(hard, ace).0);
+        let ev = if hard >= 21 { stand } else { stand.max(self.hit(hard, ace)) };
+        self.best[hard as usize][ace as usize] = Some(ev);
+
+        ev
+    }
+
+    /// Returns the expected value of doubling the bet and taking exactly one card, per unit of the original bet
+    fn double(&self, hard: u8, ace: bool) -> f64 {
+        let ev: f64 = CARD_VALUES
+            .iter()
+            .map(|&value| {
+                let new_hard = hard + if value == 11 { 1 } else { value };
+                let ev = if new_hard > 21 { -1.0 } else { self.dealer.stand(best_total(new_hard, ace || value == 11).0) };
+                card_chance(value) * ev
+            })
+            .sum();
+
+        2.0 * ev
+    }
+}
+
+/// Returns the expected value of the play for the hand against the upcard, in units of the hand's bet.
+///
+/// The values are calculated from an infinite deck, so they are estimates for shoe games. Split hands are
+/// played without splitting again
+///
+/// # Returns
+///
+/// Expected value or None if the action isn't a play for the hand
+pub fn expected_value(cards: &[Card], upcard: Rank, rules: &RuleSet, action: Action) -> Option<f64> {
+    let dealer = DealerOdds::new(upcard, rules);
+    let mut table = HitTable { dealer: &dealer, best: [[None; 2]; 22] };
+
+    let value = evaluate(cards);
+    let ace = cards.iter().any(|card| card.rank == Rank::Ace);
+    if value.is_bust() {
+        return None;
+    }
+
+    let ev = match action {
+        Action::Stand => dealer.stand(value.total()),
+        Action::Hit => table.hit(value.hard, ace),
+        Action::DoubleDown => table.double(value.hard, ace),
+        Action::Surrender => -0.5,
+        Action::Split => {
+            let HandKind::Pair(rank) = HandKind::of(cards) else {
+                return None;
+            };
+            let first = rank.value();
+
+            // Every split hand gets one more card and is played on from there
+            let hand_ev: f64 = CARD_VALUES
+                .iter()
+                .map(|&value| {
+                    let hard = (if first == 11 { 1 } else { first }) + if value == 11 { 1 } else { value };
+                    let ace = first == 11 || value == 11;
+                    let (total, _) = best_total(hard, ace);
+
+                    let ev = if first == 11 && !rules.hit_split_aces {
+                        dealer.stand(total)
+                    } else if rules.double_after_split && rules.double_rule.allows(total) {
+                        table.best(hard, ace).max(table.double(hard, ace))
+                    } else {
+                        table.best(hard, ace)
+                    };
+                    card_chance(value) * ev
+                })
+                .sum();
+
+            2.0 * hand_ev
+        },
+        Action::Insurance(_) | Action::EvenMoney | Action::Decline => return None
+    };
+
+    // Without a peek a dealer natural takes the bet, and doubles and splits too unless only the original bet is lost.
+    // Early surrender gives half of it back even then
+    let natural_loss = match action {
+        Action::Surrender if rules.surrender == SurrenderRule::Early => -0.5,
+        Action::DoubleDown | Action::Split if !rules.original_bets_only => -2.0,
+        _ => -1.0
+    };
+
+    Some((1.0 - dealer.natural) * ev + dealer.natural * natural_loss)
+}
+
+/// Returns the chance that the dealer has a natural when showing the upcard, from an infinite deck
+fn natural_chance(upcard: Rank) -> f64 {
+    match upcard.value() {
+        11 => card_chance(10),
+        10 => card_chance(11),
+        _ => 0.0
+    }
+}
+
+/// Returns the expected value of an insurance bet per unit of the insurance, against the upcard. Insurance pays 2:1
+pub fn insurance_value(upcard: Rank) -> f64 {
+    3.0 * natural_chance(upcard) - 1.0
+}
+
+/// Returns the expected value of declining even money for a natural against the upcard, in units of the bet.
+/// Taking even money is always worth exactly 1
+pub fn declined_even_money_value(upcard: Rank, rules: &RuleSet) -> f64 {
+    (1.0 - natural_chance(upcard)) * rules.blackjack_payout.ratio()
+}
+
+/// Returns the expected value of playing the hand on with basic strategy instead of surrendering early, in units of
+/// the bet. Early surrender is decided before the dealer peeks, so a dealer natural still takes the bet
+pub fn played_on_value(cards: &[Card], upcard: Rank, rules: &RuleSet) -> f64 {
+    let options = Options { double: rules.double_rule.allows(evaluate(cards).total()), split: true, surrender: false };
+    let play = basic_strategy(cards, upcard, rules, options);
+    let ev = expected_value(cards, upcard, rules, play).unwrap_or(-1.0);
+
+    // Without a peek the expected value already counts the dealer's naturals
+    if rules.hole_card != HoleCardRule::Peek {
+        return ev;
+    }
+    let natural = natural_chance(upcard);
+    (1.0 - natural) * ev - natural
+}
+
+/// Returns true if giving up half the bet before the dealer peeks is worth more than playing the hand on. Every
+/// early surrender decision goes through this: the offer before the peek, and the player's turn on a table
+/// without a peek
+pub fn should_surrender_early(cards: &[Card], upcard: Rank, rules: &RuleSet) -> bool {
+    played_on_value(cards, upcard, rules) < -0.5
+}
+
+/// A play that differs from basic strategy
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mistake {
+    pub cards: Vec<Card>,
+    pub upcard: Card,
+    pub played: Action,
+    pub correct: Action,
+    /// Bet of the hand when the play was made
+    pub bet: Money,
+    /// Expected loss of the play compared to the correct one, in units of the bet
+    pub cost: f64
+}
+
+impl Mistake {
+    pub fn situation(&self) -> Situation {
+        Situation::new(&self.cards, self.upcard.rank)
+    }
+
+    /// Returns the expected loss in money, rounded to cents
+    pub fn cost_money(&self) -> Money {
+        Money::from_cents((self.cost.max(0.0) * self.bet.cents() as f64).round() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Suit;
+
+    const ALL_OPTIONS: Options = Options { double: true, split: true, surrender: true };
+
+    /// Returns the basic strategy play for the ranks against the upcard with every option available
+    fn play(ranks: [Rank; 2], upcard: Rank, rules: &RuleSet) -> Action {
+        let cards = ranks.map(|rank| Card::new(rank, Suit::Spades));
+        basic_strategy(&cards, upcard, rules, ALL_OPTIONS)
+    }
+
+    #[test]
+    fn aces_are_split_against_a_ten_without_a_peek() {
+        for name in ["Las Vegas Strip", "No peek", "European"] {
+            let rules = RuleSet::preset(name);
+            assert_eq!(play([Rank::Ace, Rank::Ace], Rank::Ten, &rules), Action::Split, "{name}");
+            assert_eq!(play([Rank::Ace, Rank::Ace], Rank::King, &rules), Action::Split, "{name}");
+        }
+    }
+
+    #[test]
+    fn nothing_else_is_split_or_doubled_against_a_possible_natural_without_a_peek() {
+        let european = RuleSet::preset("European");
+        assert_eq!(play([Rank::Ace, Rank::Ace], Rank::Ace, &european), Action::Hit);
+        assert_eq!(play([Rank::Eight, Rank::Eight], Rank::Ten, &european), Action::Hit);
+        assert_eq!(play([Rank::Six, Rank::Five], Rank::Ten, &european), Action::Hit);
+        assert_eq!(play([Rank::Nine, Rank::Nine], Rank::Ace, &european), Action::Stand);
+
+        // Against a ten a late surrender is still worth more than hitting 16
+        assert_eq!(play([Rank::Ten, Rank::Six], Rank::Ten, &RuleSet::preset("No peek")), Action::Surrender);
+    }
+
+    #[test]
+    fn only_the_original_bet_is_lost_to_a_natural_under_obo() {
+        let obo = RuleSet { surrender: SurrenderRule::NoSurrender, ..RuleSet::preset("European OBO") };
+        assert_eq!(play([Rank::Eight, Rank::Eight], Rank::Ten, &obo), Action::Split);
+        assert_eq!(play([Rank::Six, Rank::Five], Rank::Ten, &obo), Action::DoubleDown);
+        assert_eq!(play([Rank::Ace, Rank::Ace], Rank::Ace, &obo), Action::Split);
+    }
+
+    #[test]
+    fn early_surrender_without_a_peek_replaces_the_late_chart() {
+        let rules = RuleSet::preset("European OBO");
+        assert_eq!(play([Rank::Eight, Rank::Eight], Rank::Ten, &rules), Action::Surrender);
+        assert_eq!(play([Rank::Ten, Rank::Four], Rank::Ten, &rules), Action::Surrender);
+        assert_eq!(play([Rank::Ten, Rank::Three], Rank::Nine, &rules), Action::Hit);
+        assert_eq!(play([Rank::Ten, Rank::Seven], Rank::Ten, &rules), Action::Stand);
+
+        // The late chart doesn't surrender 14 against a ten
+        let late = RuleSet { surrender: SurrenderRule::Late, ..rules };
+        assert_eq!(play([Rank::Ten, Rank::Four], Rank::Ten, &late), Action::Hit);
+    }
+
+    #[test]
+    fn surrender_after_the_peek_follows_the_late_chart_on_early_surrender_tables() {
+        let rules = RuleSet { surrender: SurrenderRule::Early, ..RuleSet::default() };
+        let hand = [Rank::Ten, Rank::Four].map(|rank| Card::new(rank, Suit::Spades));
+
+        // 14 is given up before the peek, but not once the dealer is known not to have a natural
+        assert!(should_surrender_early(&hand, Rank::Ten, &rules));
+        assert_eq!(basic_strategy(&hand, Rank::Ten, &rules, ALL_OPTIONS), Action::Hit);
+        assert_eq!(play([Rank::Ten, Rank::Six], Rank::Ten, &rules), Action::Surrender);
+    }
+
+    #[test]
+    fn early_surrender_gives_up_weak_hands_against_a_ten_or_an_ace() {
+        let rules = RuleSet { surrender: SurrenderRule::Early, ..RuleSet::default() };
+        let cards = |ranks: [Rank; 2]| ranks.map(|rank| Card::new(rank, Suit::Spades));
+
+        assert!(should_surrender_early(&cards([Rank::Ten, Rank::Six]), Rank::Ten, &rules));
+        assert!(should_surrender_early(&cards([Rank::Ten, Rank::Four]), Rank::Ten, &rules));
+        assert!(should_surrender_early(&cards([Rank::Ten, Rank::Six]), Rank::Ace, &rules));
+        assert!(!should_surrender_early(&cards([Rank::Ten, Rank::Eight]), Rank::Ten, &rules));
+        assert!(!should_surrender_early(&cards([Rank::Six, Rank::Five]), Rank::Ten, &rules));
+    }
+
+    #[test]
+    fn peeked_tables_play_the_usual_chart() {
+        let rules = RuleSet::default();
+        assert_eq!(play([Rank::Eight, Rank::Eight], Rank::Ten, &rules), Action::Split);
+        assert_eq!(play([Rank::Six, Rank::Five], Rank::Ten, &rules), Action::DoubleDown);
+        assert_eq!(play([Rank::Six, Rank::Five], Rank::Ace, &rules), Action::Hit);
+    }
+}
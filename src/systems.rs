@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::PathBuf;
+
+use blackjack::count::CountingSystem;
+
+use crate::save::save_dir;
+
+// Custom counting systems are text files in this directory of the save directory
+const SYSTEMS_DIR: &str = "systems";
+const SYSTEM_EXTENSION: &str = "txt";
+
+/// Returns the directory custom counting systems are read from
+pub fn systems_dir() -> PathBuf {
+    save_dir().join(SYSTEMS_DIR)
+}
+
+/// Reads every counting system: the built-in ones followed by the custom ones in file name order
+///
+/// # Returns
+///
+/// Systems that could be read and a description of every file that couldn't be
+pub fn load_systems() -> (Vec<CountingSystem>, Vec<String>) {
+    let mut systems = CountingSystem::built_in();
+    let mut errors = Vec::new();
+
+    // Without the directory there are no custom systems
+    let Ok(entries) = fs::read_dir(systems_dir()) else {
+        return (systems, errors);
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == SYSTEM_EXTENSION))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        let system = match fs::read_to_string(&path) {
+            Ok(text) => text.parse::<CountingSystem>(),
+            Err(err) => Err(err.to_string())
+        };
+
+        match system {
+            Ok(system) if systems.iter().any(|other| other.name.eq_ignore_ascii_case(&system.name)) => {
+                errors.push(format!("{file_name}: There is already a counting system called {}", system.name));
+            },
+            Ok(system) => systems.push(system),
+            Err(err) => errors.push(format!("{file_name}: {err}"))
+        };
+    }
+
+    (systems, errors)
+}
+
+/// Returns the counting system with the passed name, or None if there is none or its file can't be read
+pub fn find_system(name: &str) -> Option<CountingSystem> {
+    load_systems().0.into_iter().find(|system| system.name.eq_ignore_ascii_case(name))
+}
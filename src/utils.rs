@@ -1,7 +1,111 @@
-use std::thread::sleep;
-use std::time::Duration;
+use std::fmt;
 use std::io;
 use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Something that can wait. The game waits only through the clock of the current pacing
+pub trait Clock {
+    fn sleep(&self, duration: Duration);
+}
+
+/// Clock that waits the full time
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Clock that waits a quarter of the time
+pub struct FastClock;
+
+impl Clock for FastClock {
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration / 4);
+    }
+}
+
+/// Clock that doesn't wait at all
+pub struct InstantClock;
+
+impl Clock for InstantClock {
+    fn sleep(&self, _duration: Duration) {}
+}
+
+// Current pacing stored as its index in Pacing::ALL
+static PACING: AtomicU8 = AtomicU8::new(0);
+
+/// Enum for how long the game waits so that the player can follow what happens
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pacing {
+    #[default]
+    Normal,
+    /// Every wait takes a quarter of the normal time
+    Fast,
+    /// Nothing waits and notifications stay on the screen
+    Instant
+}
+
+impl Pacing {
+    pub const ALL: [Pacing; 3] = [Pacing::Normal, Pacing::Fast, Pacing::Instant];
+
+    /// Returns the clock the game waits on at this pacing
+    pub fn clock(&self) -> &'static dyn Clock {
+        match self {
+            Pacing::Normal => &SystemClock,
+            Pacing::Fast => &FastClock,
+            Pacing::Instant => &InstantClock
+        }
+    }
+
+    /// Returns the next pacing, going back to the first one after the last
+    pub fn next(&self) -> Pacing {
+        let index = Pacing::ALL.iter().position(|pacing| pacing == self).unwrap_or(0);
+        Pacing::ALL[(index + 1) % Pacing::ALL.len()]
+    }
+}
+
+impl fmt::Display for Pacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pacing::Normal => write!(f, "normal"),
+            Pacing::Fast => write!(f, "fast"),
+            Pacing::Instant => write!(f, "instant")
+        }
+    }
+}
+
+impl FromStr for Pacing {
+    type Err = String;
+
+    /// Reads a pacing from its name, e.g. "fast"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pacing::ALL
+            .into_iter()
+            .find(|pacing| pacing.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown pacing '{s}'. Use normal, fast or instant"))
+    }
+}
+
+/// Sets how long the game waits from now on
+pub fn set_pacing(pacing: Pacing) {
+    let index = Pacing::ALL.iter().position(|p| *p == pacing).unwrap_or(0);
+    PACING.store(index as u8, Ordering::Relaxed);
+}
+
+/// Returns how long the game waits
+pub fn pacing() -> Pacing {
+    Pacing::ALL.get(PACING.load(Ordering::Relaxed) as usize).copied().unwrap_or_default()
+}
+
+/// Waits on the clock of the current pacing. The passed duration is the wait at normal pacing
+pub fn pause(duration: Duration) {
+    pacing().clock().sleep(duration);
+}
 
 /// Enum contains durations for notifications. This keeps the durations consistent.
 pub enum NotificationDuration {
@@ -19,10 +123,14 @@ impl NotificationDuration {
     }
 }
 
-/// Prints a temporary message for entered amount of seconds
+/// Prints a temporary message for entered amount of seconds. With instant pacing the message stays on the screen
 pub fn notification(msg: &str, duration: NotificationDuration) {
     println!("{msg}");
-    sleep(duration.as_duration());
+    if pacing() == Pacing::Instant {
+        return;
+    }
+
+    pause(duration.as_duration());
     print!("\x1B[A\r\x1B[K");
     io::stdout().flush().unwrap();
 }
@@ -31,4 +139,4 @@ pub fn notification(msg: &str, duration: NotificationDuration) {
 pub fn clear_terminal() {
     print!("\x1B[2J\x1B[1;1H");
     io::stdout().flush().unwrap();
-}
\ No newline at end of file
+}